@@ -0,0 +1,81 @@
+//! # breadcrumbs-derive
+//! Derive macros for [`breadcrumbs`](https://docs.rs/breadcrumbs). Enable them through the `derive` feature of `breadcrumbs` rather than depending on this crate directly.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// Implements `breadcrumbs::LogChannel` for an enum, using the name of each variant as its channel.
+///
+/// ```rust,ignore
+/// use breadcrumbs::{log, LogChannel, LogLevel};
+///
+/// #[derive(LogChannel)]
+/// enum Channel {
+///     Network,
+///     Storage,
+/// }
+///
+/// log!(LogLevel::Info, Channel::Network, "Connected");
+/// ```
+#[proc_macro_derive(LogChannel)]
+pub fn derive_log_channel(input: TokenStream) -> TokenStream {
+    match expand_log_channel(input) {
+        Ok(output) => output,
+        Err(message) => format!("::core::compile_error!({:?});", message).parse().unwrap(),
+    }
+}
+
+fn expand_log_channel(input: TokenStream) -> Result<TokenStream, &'static str> {
+    let mut tokens = input.into_iter();
+
+    // Skip attributes and visibility until the `enum` keyword.
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "enum" => break,
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" || ident.to_string() == "union" => {
+                return Err("`LogChannel` can only be derived for enums");
+            }
+            Some(_) => continue,
+            None => return Err("`LogChannel` can only be derived for enums"),
+        }
+    }
+
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected an enum name"),
+    };
+
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("`LogChannel` cannot be derived for generic enums");
+        }
+        _ => return Err("expected an enum body"),
+    };
+
+    let mut arms = String::new();
+    let mut expecting_variant = true;
+    let mut body = body.into_iter().peekable();
+    while let Some(token) = body.next() {
+        match token {
+            // Variant attributes, e.g. `#[doc = "..."]`.
+            TokenTree::Punct(punct) if punct.as_char() == '#' && expecting_variant => {
+                body.next();
+            }
+            TokenTree::Ident(ident) if expecting_variant => {
+                let variant = ident.to_string();
+                arms.push_str(&format!("{}::{} {{ .. }} => {:?},", name, variant, variant));
+                expecting_variant = false;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == ',' => expecting_variant = true,
+            // Tuple or struct fields, and explicit discriminants.
+            _ => {}
+        }
+    }
+
+    Ok(format!(
+        "impl ::breadcrumbs::LogChannel for {} {{ fn channel_name(&self) -> &'static str {{ match *self {{ {} }} }} }}",
+        name, arms
+    )
+    .parse()
+    .unwrap())
+}
@@ -10,46 +10,68 @@
 //! - Seamless integration with `#![no_std]` and `#[no_panic]`
 //! - Multi-threading and concurrent logging with no special syntax
 //! - Easy-to-use macros
-//! - Support for listeners to be notified of new logs
+//! - Support for multiple listeners to be notified of new logs
+//! - Automatic capture of the source-code location of each log
 
 // Import the necessary crates
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(all(test, feature = "derive"))]
+extern crate self as breadcrumbs;
 use alloc::{
+    vec,
     vec::Vec,
     sync::Arc,
     boxed::Box,
-    string::String,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    string::{String, ToString},
     format
 };
+#[cfg(not(feature = "no_global"))]
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering};
+#[cfg(not(feature = "no_global"))]
 use lazy_static::lazy_static;
 use spin::Mutex;
 
 /// Enum representing different log levels.
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default, Hash)]
 pub enum LogLevel {
     Verbose,
+    Debug,
+    #[default]
     Info,
     Warn,
     Error,
     Critical,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        LogLevel::Info
-    }
-}
+/// Every log level, in ascending order of severity.
+/// ```rust
+/// use breadcrumbs::{LogLevel, ALL_LOG_LEVELS};
+/// assert_eq!(ALL_LOG_LEVELS[0], LogLevel::Verbose);
+/// assert_eq!(ALL_LOG_LEVELS[ALL_LOG_LEVELS.len() - 1], LogLevel::Critical);
+/// ```
+pub const ALL_LOG_LEVELS: [LogLevel; 6] = [
+    LogLevel::Verbose,
+    LogLevel::Debug,
+    LogLevel::Info,
+    LogLevel::Warn,
+    LogLevel::Error,
+    LogLevel::Critical,
+];
 
 impl core::fmt::Display for LogLevel {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let level_str = match self {
             LogLevel::Verbose => "Verbose",
+            LogLevel::Debug => "Debug",
             LogLevel::Info => "Info",
             LogLevel::Warn => "Warn",
             LogLevel::Error => "Error",
             LogLevel::Critical => "Critical",
         };
-        write!(f, "{}", level_str)
+        f.pad(level_str)
     }
 }
 impl LogLevel {
@@ -58,371 +80,7881 @@ impl LogLevel {
     /// use breadcrumbs::LogLevel;
     /// let log_level = LogLevel::Info;
     /// assert!(log_level.is_at_least(LogLevel::Info));
+    /// assert!(log_level.is_at_least(LogLevel::Debug));
     /// assert!(log_level.is_at_least(LogLevel::Verbose));
     /// assert!(!log_level.is_at_least(LogLevel::Warn));
     /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
     pub fn is_at_least(&self, level: LogLevel) -> bool {
-        match level {
-            LogLevel::Verbose => true,
-            LogLevel::Info => self != &LogLevel::Verbose,
-            LogLevel::Warn => self != &LogLevel::Verbose && self != &LogLevel::Info,
-            LogLevel::Error => self != &LogLevel::Verbose && self != &LogLevel::Info && self != &LogLevel::Warn,
-            LogLevel::Critical => self == &LogLevel::Critical,
+        *self >= level
+    }
+
+    /// Returns the name of the log level right-padded with spaces to the length of the longest name, `"Critical"`,
+    /// so that text following it lines up in a column.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::Info.label_padded(), "Info    ");
+    /// assert_eq!(LogLevel::Critical.label_padded(), "Critical");
+    /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn label_padded(&self) -> &'static str {
+        match self {
+            LogLevel::Verbose => "Verbose ",
+            LogLevel::Debug => "Debug   ",
+            LogLevel::Info => "Info    ",
+            LogLevel::Warn => "Warn    ",
+            LogLevel::Error => "Error   ",
+            LogLevel::Critical => "Critical",
+        }
+    }
+
+    /// The ANSI escape sequence that resets the terminal colors set by `color_code()`.
+    #[cfg(feature = "ansi-colors")]
+    pub const RESET_CODE: &'static str = "\x1b[0m";
+
+    /// Returns the ANSI escape sequence that sets the terminal color used for the log level.
+    /// This requires the `ansi-colors` feature.
+    /// ```rust
+    /// # #[cfg(feature = "ansi-colors")]
+    /// # {
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::Warn.color_code(), "\x1b[33m");
+    /// let colored = format!("{}Warn{}", LogLevel::Warn.color_code(), LogLevel::RESET_CODE);
+    /// # }
+    /// ```
+    #[cfg(feature = "ansi-colors")]
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn color_code(&self) -> &'static str {
+        match self {
+            LogLevel::Verbose => "\x1b[37m",
+            LogLevel::Debug => "\x1b[36m",
+            LogLevel::Info => "\x1b[32m",
+            LogLevel::Warn => "\x1b[33m",
+            LogLevel::Error => "\x1b[31m",
+            LogLevel::Critical => "\x1b[1;91m",
         }
     }
 
+    /// Returns the least severe log level, which every log is at least as severe as.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// assert!(LogLevel::Verbose.is_at_least(LogLevel::all()));
+    /// ```
+    pub fn all() -> LogLevel {
+        LogLevel::Verbose
+    }
+
+    /// Returns an iterator over every log level, in ascending order of severity.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// let levels: Vec<LogLevel> = LogLevel::all_levels().collect();
+    /// assert_eq!(levels.len(), 6);
+    /// assert_eq!(levels[0], LogLevel::Verbose);
+    /// assert_eq!(levels[5], LogLevel::Critical);
+    /// ```
+    pub fn all_levels() -> impl Iterator<Item = LogLevel> {
+        ALL_LOG_LEVELS.into_iter()
+    }
+
+    /// Parses a log level from its name, defaulting to `LogLevel::Info` for unknown names.
+    #[deprecated(note = "unknown names silently become `LogLevel::Info`; use `LogLevel::try_from_str` or `str::parse` instead")]
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(level: &str) -> LogLevel {
+        LogLevel::try_from_str(level).unwrap_or(LogLevel::Info)
+    }
+
+    /// Parses a log level from its name, returning an error for unknown names.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::try_from_str("Warn"), Ok(LogLevel::Warn));
+    /// assert!(LogLevel::try_from_str("Loud").is_err());
+    /// ```
+    pub fn try_from_str(level: &str) -> Result<LogLevel, UnknownLogLevel> {
         match level {
-            "Verbose" => LogLevel::Verbose,
-            "Info" => LogLevel::Info,
-            "Warn" => LogLevel::Warn,
-            "Error" => LogLevel::Error,
-            "Critical" => LogLevel::Critical,
-            _ => LogLevel::Info,
+            "Verbose" => Ok(LogLevel::Verbose),
+            "Debug" => Ok(LogLevel::Debug),
+            "Info" => Ok(LogLevel::Info),
+            "Warn" => Ok(LogLevel::Warn),
+            "Error" => Ok(LogLevel::Error),
+            "Critical" => Ok(LogLevel::Critical),
+            _ => Err(UnknownLogLevel(String::from(level))),
         }
     }
-}
 
-/// Represents a log entry.
-/// `Log` beautifully implements `Display` for easy printing.
-/// ```rust
-/// use breadcrumbs::Log;
-/// let log = Log::new(String::from("test_channel"), breadcrumbs::LogLevel::Info, String::from("Test log message"));
-/// assert_eq!(format!("{}", log), "[test_channel/Info] Test log message");
-/// ```
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
-pub struct Log {
-    pub channel: String,
-    pub level: LogLevel,
-    pub message: String,
-}
+    /// The version of the numbering used by `numeric()` and `from_numeric()`.
+    /// Store it alongside serialized numeric levels so that a change in numbering can be detected;
+    /// version 1 had no `Debug` level and numbered `Verbose` to `Critical` from 0 to 4.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::NUMERIC_VERSION, 2);
+    /// ```
+    pub const NUMERIC_VERSION: u8 = 2;
 
-impl core::fmt::Display for Log {
-    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        if self.channel != "" {
-            return write!(f, "[{}/{}] {}", self.channel, self.level, self.message);
-        } else {
-            return write!(f, "[{}] {}", self.level, self.message)
+    /// Returns the numeric value of the log level, from 0 for `Verbose` to 5 for `Critical`.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::Verbose.numeric(), 0);
+    /// assert_eq!(LogLevel::Debug.numeric(), 1);
+    /// assert_eq!(LogLevel::Critical.numeric(), 5);
+    /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn numeric(self) -> u8 {
+        match self {
+            LogLevel::Verbose => 0,
+            LogLevel::Debug => 1,
+            LogLevel::Info => 2,
+            LogLevel::Warn => 3,
+            LogLevel::Error => 4,
+            LogLevel::Critical => 5,
         }
     }
-}
 
-impl Log {
-    /// Creates a new log entry.
-    pub fn new(channel: String, level: LogLevel, message: String) -> Log {
-        Log {
-            channel,
-            level,
-            message,
+    /// Creates a log level from its numeric value, returning `None` for values above 5.
+    /// ```rust
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::from_numeric(3), Some(LogLevel::Warn));
+    /// assert_eq!(LogLevel::from_numeric(6), None);
+    /// ```
+    pub fn from_numeric(n: u8) -> Option<LogLevel> {
+        match n {
+            0 => Some(LogLevel::Verbose),
+            1 => Some(LogLevel::Debug),
+            2 => Some(LogLevel::Info),
+            3 => Some(LogLevel::Warn),
+            4 => Some(LogLevel::Error),
+            5 => Some(LogLevel::Critical),
+            _ => None,
         }
     }
-    /// Removes the log from the stored traceback of logs.
-    /// This log will not use up memory or be printed by the traceback macros.
-    /// Useful in embedded systems where memory is limited.
-    /// 
-    /// # Example Usecase
+
+    /// Creates a log level from a numeric value stored with version 1 of the numbering, before `Debug` was added.
     /// ```rust
-    /// use breadcrumbs::{LogListener, Log};
-    /// 
-    /// struct MyLogListener;
-    /// impl LogListener for MyLogListener {
-    ///     fn on_log(&mut self, log: Log) {
-    ///         if !log.level.is_at_least(breadcrumbs::LogLevel::Warn) {
-    ///             log.remove();
-    ///         }
-    ///     }
-    /// }
+    /// use breadcrumbs::LogLevel;
+    /// assert_eq!(LogLevel::from_legacy_numeric(2), Some(LogLevel::Warn));
+    /// assert_eq!(LogLevel::from_legacy_numeric(5), None);
     /// ```
-    pub fn remove(&self) {
-        let mut logs = LOGS.lock();
-        let index = logs.iter().position(|log| log == self).unwrap();
-        logs.remove(index);
+    #[deprecated(note = "only needed to read numeric levels stored before `LogLevel::Debug` was added; use `LogLevel::from_numeric` for new data")]
+    pub fn from_legacy_numeric(n: u8) -> Option<LogLevel> {
+        match n {
+            0 => Some(LogLevel::Verbose),
+            1..=4 => LogLevel::from_numeric(n + 1),
+            _ => None,
+        }
     }
 }
 
-/// A trait for handling log entries.
-pub trait LogListener: Send + Sync {
-    fn on_log(&mut self, log: Log);
+#[cfg(feature = "serde")]
+impl serde::Serialize for LogLevel {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
-lazy_static! {
-    static ref LOGS: Arc<Mutex<Vec<Log>>> = Arc::new(Mutex::new(Vec::new()));
-    static ref LOG_LISTENER: Arc<Mutex<Option<Box<dyn LogListener>>>> = Arc::new(Mutex::new(None));
+/// Log levels are deserialized from their names ignoring case, and unknown names become `LogLevel::Info`.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LogLevel {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<LogLevel, D::Error> {
+        struct LevelVisitor;
+
+        impl serde::de::Visitor<'_> for LevelVisitor {
+            type Value = LogLevel;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                f.write_str("a log level name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, level: &str) -> Result<LogLevel, E> {
+                Ok(ALL_LOG_LEVELS.into_iter().find(|known| known.to_string().eq_ignore_ascii_case(level)).unwrap_or(LogLevel::Info))
+            }
+        }
+
+        deserializer.deserialize_str(LevelVisitor)
+    }
 }
 
-/// Initializes the logging system without a listener.
-/// Note that the `init!` macro is the preferred method to do this in the public API.
-/// ```rust
-/// use breadcrumbs::init;
-/// init();
-/// ```
-pub fn init() {
-    LOGS.lock().clear();
-    *LOG_LISTENER.lock() = None;
+impl TryFrom<u8> for LogLevel {
+    type Error = UnknownLogLevelNumber;
+
+    fn try_from(n: u8) -> Result<LogLevel, UnknownLogLevelNumber> {
+        LogLevel::from_numeric(n).ok_or(UnknownLogLevelNumber(n))
+    }
 }
 
-/// Initializes the logging system with a listener.
-/// Note that the `init!` macro is the preferred method to do this in the public API.
+/// The error returned when converting an out-of-range number into a `LogLevel`.
+/// Carries the number that failed to convert.
 /// ```rust
-/// use breadcrumbs::{init_with_listener, LogListener};
-/// struct MyLogListener;
-/// 
-/// impl LogListener for MyLogListener {
-///    fn on_log(&mut self, log: breadcrumbs::Log) {
-///       println!("{}", log);
-///   }
-/// }
-/// 
-/// init_with_listener(Box::new(MyLogListener));
+/// use breadcrumbs::{LogLevel, UnknownLogLevelNumber};
+/// assert_eq!(LogLevel::try_from(7), Err(UnknownLogLevelNumber(7)));
 /// ```
-pub fn init_with_listener(listener: Box<dyn LogListener>) {
-    LOGS.lock().clear();
-    *LOG_LISTENER.lock() = Some(listener);
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct UnknownLogLevelNumber(pub u8);
+
+impl core::fmt::Display for UnknownLogLevelNumber {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "unknown log level number: {}", self.0)
+    }
 }
 
+impl core::str::FromStr for LogLevel {
+    type Err = UnknownLogLevel;
 
-/// A macro for initializing the logging system.
-/// 
-/// # Use
-/// 
-/// To initialize the logging system without a listener, do not pass any arguments.
-/// 
-/// To initialize the logging system with a listener, pass a listener implementing `LogListener` as the first argument.
-/// 
-/// # Examples
-/// 
-/// Initialize the logging system without a listener:
-/// ```
-/// use breadcrumbs::init;
-/// init!();
-/// ```
-/// 
-/// Initialize the logging system with a listener:
+    fn from_str(level: &str) -> Result<LogLevel, UnknownLogLevel> {
+        LogLevel::try_from_str(level)
+    }
+}
+
+/// The error returned when parsing an unrecognized log level name.
+/// Carries the name that failed to parse.
+/// ```rust
+/// use breadcrumbs::{LogLevel, UnknownLogLevel};
+/// assert_eq!("Loud".parse::<LogLevel>(), Err(UnknownLogLevel(String::from("Loud"))));
 /// ```
-/// use breadcrumbs::{init, LogListener};
-/// struct MyLogListener;
-/// 
-/// impl LogListener for MyLogListener {
-///   fn on_log(&mut self, log: breadcrumbs::Log) {
-///      println!("{}", log);
-///   }
-/// }
-/// 
-/// init!(MyLogListener);
-#[macro_export]
-macro_rules! init {
-    () => {
-        $crate::init()
-    };
-    ($arg1:expr) => {
-        extern crate alloc;
-        use alloc::boxed::Box;
-        $crate::init_with_listener(Box::new($arg1))
-    };
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct UnknownLogLevel(pub String);
+
+impl core::fmt::Display for UnknownLogLevel {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "unknown log level: {}", self.0)
+    }
 }
 
-/// Logs a message with an optional log level and channel. 
-/// Note that the `log!` macro is the preferred method to do this in the public API.
+/// A type that names a log channel.
+/// Implementing this for an enum lets the compiler catch misspelled channel names, and string literals implement it for backward compatibility.
+/// With the `derive` feature enabled, `#[derive(LogChannel)]` implements it for enums using each variant's name as the channel.
 /// ```rust
-/// use breadcrumbs::{log, LogLevel};
-/// log(Some(LogLevel::Info), Some(String::from("test_channel")), String::from("Test log message"));
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{log, log_to_channel, LogChannel, LogLevel};
+///
+/// enum Channel {
+///     Network,
+///     Storage,
+/// }
+///
+/// impl LogChannel for Channel {
+///     fn channel_name(&self) -> &'static str {
+///         match self {
+///             Channel::Network => "Network",
+///             Channel::Storage => "Storage",
+///         }
+///     }
+/// }
+///
+/// log_to_channel(Some(LogLevel::Info), Channel::Network, String::from("Connected"));
+/// log!(LogLevel::Warn, Channel::Storage, "Disk almost full");
+/// # }
 /// ```
-pub fn log(level: Option<LogLevel>, channel: Option<String>, message: String) {
-    let log = Log::new(channel.unwrap_or(String::from("")), level.unwrap_or(LogLevel::Info), message.clone());
-    LOGS.lock().push(log.clone());
-    if let Some(listener) = &mut *LOG_LISTENER.lock() {
-        listener.on_log(Log::new(log.channel, log.level, log.message));
+pub trait LogChannel {
+    /// Returns the name of the channel.
+    fn channel_name(&self) -> &'static str;
+}
+
+impl LogChannel for &'static str {
+    fn channel_name(&self) -> &'static str {
+        self
     }
 }
 
-/// Represents a traceback of logs.
-/// `Traceback` beautifully implements `Display` for easy printing.
+#[cfg(feature = "derive")]
+pub use breadcrumbs_derive::LogChannel;
+
+/// Converts the channel argument of the logging macros into a channel name.
+/// This accepts any `LogChannel` as well as owned strings, and is not part of the public API.
+#[doc(hidden)]
+pub trait _ChannelArg {
+    fn _channel_name(&self) -> &str;
+}
+
+impl<T: LogChannel> _ChannelArg for T {
+    fn _channel_name(&self) -> &str {
+        self.channel_name()
+    }
+}
+
+impl _ChannelArg for String {
+    fn _channel_name(&self) -> &str {
+        self
+    }
+}
+
+impl _ChannelArg for &String {
+    fn _channel_name(&self) -> &str {
+        self
+    }
+}
+
+/// A filter on log levels, used by the traceback and counting functions.
+/// `LogLevel` and `Option<LogLevel>` convert into it, with `None` meaning that every level passes.
 /// ```rust
-/// use breadcrumbs::{Traceback, Log};
-/// let traceback = Traceback(vec![Log::new(String::from("test_channel"), breadcrumbs::LogLevel::Info, String::from("Test log message"))]);
-/// assert_eq!(format!("{}", traceback), "[test_channel/Info] Test log message\n");
+/// use breadcrumbs::{LevelFilter, LogLevel};
+/// assert!(LevelFilter::All.matches(LogLevel::Verbose));
+/// assert!(LevelFilter::from(LogLevel::Warn).matches(LogLevel::Error));
+/// assert!(!LevelFilter::from(LogLevel::Warn).matches(LogLevel::Info));
+/// assert!(!LevelFilter::None.matches(LogLevel::Critical));
 /// ```
-pub struct Traceback(pub Vec<Log>);
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum LevelFilter {
+    /// Every level passes.
+    #[default]
+    All,
+    /// Levels at least as severe as the given one pass.
+    AtLeast(LogLevel),
+    /// No level passes.
+    None,
+}
 
-impl Traceback {
-    /// Converts the traceback to a beautifully-formatted string.
+impl LevelFilter {
+    /// Checks if the given level passes the filter.
     /// ```rust
-    /// use breadcrumbs::traceback;
-    /// let traceback = traceback!();
-    /// let traceback_string = traceback.to_string();
+    /// use breadcrumbs::{LevelFilter, LogLevel};
+    /// assert!(LevelFilter::AtLeast(LogLevel::Info).matches(LogLevel::Info));
     /// ```
-    pub fn to_string(&self) -> String {
-        let mut traceback = String::new();
-        for log in &self.0 {
-            traceback.push_str(&format!("{}\n", log));
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn matches(self, level: LogLevel) -> bool {
+        match self {
+            LevelFilter::All => true,
+            LevelFilter::AtLeast(min_level) => level.is_at_least(min_level),
+            LevelFilter::None => false,
         }
-        traceback
     }
 }
 
-impl core::fmt::Display for Traceback {
-    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        write!(f, "{}", self.to_string())
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> LevelFilter {
+        LevelFilter::AtLeast(level)
     }
 }
 
-/// Retrieves a traceback of logs based on the minimum log level and channel filter.
-/// Note that the `traceback!` macro is the preferred method to do this in the public API.
-/// ```rust
-/// use breadcrumbs::{get_logs_traceback, LogLevel};
-/// let traceback = get_logs_traceback(Some(LogLevel::Warn), Some(vec![String::from("test_channel")]));
-/// ```
-pub fn get_logs_traceback(min_level: Option<LogLevel>, channels: Option<Vec<String>>) -> Traceback {
-    let mut logs = Vec::new();
-    for log in LOGS.lock().iter() {
-        if min_level.is_some() && !log.level.is_at_least(min_level.unwrap()) {
-            continue;
-        }
-        if channels.is_some() && !channels.as_ref().unwrap().contains(&log.channel) {
-            continue;
+impl From<Option<LogLevel>> for LevelFilter {
+    fn from(level: Option<LogLevel>) -> LevelFilter {
+        match level {
+            Some(level) => LevelFilter::AtLeast(level),
+            None => LevelFilter::All,
         }
-        logs.push(log.clone());
     }
-    Traceback(logs)
 }
 
-/// A macro for generating a `Traceback` of logs, optionally filtered by log level and channel.
-/// 
-/// To only specify a `LogLevel`, use the `traceback_level!` macro.
-/// 
-/// To only specify a `channel`, use the `traceback_channel!` macro.
-/// 
-/// # Examples
-/// 
-/// Traceback with default values:
-/// 
-/// ```
-/// use breadcrumbs::traceback;
-/// let traceback = traceback!();
-/// ```
-/// 
-/// Traceback with a custom log level and channel:
-/// 
+/// The channel of logs made without a channel.
+/// Prefer it over a bare `""` when constructing or matching such logs.
+/// ```rust
+/// use breadcrumbs::{Log, LogLevel, NO_CHANNEL};
+/// let log = Log::new(String::from(NO_CHANNEL), LogLevel::Info, String::from("Test log message"));
+/// assert!(log.is_empty_channel());
+/// assert_eq!(format!("{}", log), "[Info    ] Test log message");
 /// ```
-/// use breadcrumbs::{traceback, LogLevel};
-/// let traceback = traceback!(LogLevel::Warn, "test_channel");
+pub const NO_CHANNEL: &str = "";
+
+/// Represents a log entry.
+/// `Log` beautifully implements `Display` for easy printing.
+/// ```rust
+/// use breadcrumbs::Log;
+/// let log = Log::new(String::from("test_channel"), breadcrumbs::LogLevel::Info, String::from("Test log message"));
+/// assert_eq!(format!("{}", log), "[test_channel/Info    ] Test log message");
 /// ```
-#[macro_export]
-macro_rules! traceback {
-    () => {
-        $crate::get_logs_traceback(None, None)
-    };
-    ($arg1:expr, $arg2:expr) => {
-        $crate::get_logs_traceback(Some($arg1), Some(vec![$arg2.to_string()]))
-    };
+///
+/// With the `serde` feature enabled, `Log` implements `Serialize` and `Deserialize`. Only the fields that are set
+/// are serialized, so a plain log becomes `{"channel":"c","level":"Info","message":"m"}`.
+/// The source-code location is not serialized.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Log {
+    pub channel: String,
+    /// The channels the log belongs to besides `channel`, for logs made with `Log::new_multi`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub extra_channels: Vec<String>,
+    pub level: LogLevel,
+    pub message: String,
+    /// The source file the log originated from, if known.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub file: Option<&'static str>,
+    /// The source line the log originated from, if known.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub line: Option<u32>,
+    /// The source column the log originated from, if known.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub column: Option<u32>,
+    /// Structured key-value pairs attached to the log, if any.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub metadata: Option<Vec<(String, String)>>,
+    /// How many times this log occurred: 1, plus the number of consecutive identical logs folded into it while deduplication is enabled.
+    /// This is the count shown as `(×n)` when the log is displayed.
+    #[cfg_attr(feature = "serde", serde(default = "one", skip_serializing_if = "is_one"))]
+    pub occurrences: u32,
+    /// A globally monotonic number assigned when the log is stored, or 0 for manually constructed logs.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_zero"))]
+    pub sequence: u64,
+    /// The tick value returned by the registered clock when the log was stored, if a clock is registered.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub timestamp: Option<u64>,
 }
 
-/// A macro for generating a `Traceback` of logs given only a log level.
-/// 
-/// # Examples
-/// 
-/// Basic usage:
-/// 
-/// ```
-/// use breadcrumbs::{traceback_level, LogLevel};
-/// let traceback = traceback_level!(LogLevel::Warn);
+#[cfg(feature = "serde")]
+fn is_zero<T: Default + PartialEq>(n: &T) -> bool {
+    *n == T::default()
+}
+
+#[cfg(feature = "serde")]
+fn one() -> u32 {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn is_one(n: &u32) -> bool {
+    *n == 1
+}
+
+impl core::fmt::Display for Log {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.fmt_header(f)?;
+        write!(f, "{}] ", self.level.label_padded())?;
+        self.fmt_body(f)
+    }
+}
+
+impl Log {
+    /// Writes the opening bracket of the log along with its timestamp and channels, up to the level.
+    fn fmt_header(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "[")?;
+        if let Some(timestamp) = self.timestamp {
+            write!(f, "{}/", timestamp)?;
+        }
+        if !self.is_empty_channel() {
+            write!(f, "{}", self.channel)?;
+            for channel in &self.extra_channels {
+                write!(f, ",{}", channel)?;
+            }
+            write!(f, "/")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the log like its `Display` implementation, with the level wrapped in an ANSI color code.
+    #[cfg(feature = "ansi-colors")]
+    fn fmt_ansi(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.fmt_header(f)?;
+        write!(f, "{}{}{}] ", self.level.color_code(), self.level.label_padded(), LogLevel::RESET_CODE)?;
+        self.fmt_body(f)
+    }
+
+    /// Writes the message of the log along with its occurrence count, metadata and location.
+    fn fmt_body(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if self.occurrences > 1 {
+            write!(f, " (×{})", self.occurrences)?;
+        }
+        if let Some(metadata) = &self.metadata {
+            for (key, value) in metadata {
+                write!(f, " {}={}", key, value)?;
+            }
+        }
+        if let (Some(file), Some(line)) = (self.file, self.line) {
+            write!(f, " @ {}:{}", file, line)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a new log entry.
+    pub fn new(channel: String, level: LogLevel, message: String) -> Log {
+        Log {
+            channel,
+            extra_channels: Vec::new(),
+            level,
+            message,
+            file: None,
+            line: None,
+            column: None,
+            metadata: None,
+            occurrences: 1,
+            sequence: 0,
+            timestamp: None,
+        }
+    }
+
+    /// Creates a new log entry of level `Verbose`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_verbose("net", "Entering main loop");
+    /// assert_eq!(log, Log::new(String::from("net"), LogLevel::Verbose, String::from("Entering main loop")));
+    /// ```
+    pub fn new_verbose(channel: impl Into<String>, message: impl Into<String>) -> Log {
+        Log::new(channel.into(), LogLevel::Verbose, message.into())
+    }
+
+    /// Creates a new log entry of level `Debug`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_debug("net", "Buffer at 0x2000");
+    /// assert_eq!(log, Log::new(String::from("net"), LogLevel::Debug, String::from("Buffer at 0x2000")));
+    /// ```
+    pub fn new_debug(channel: impl Into<String>, message: impl Into<String>) -> Log {
+        Log::new(channel.into(), LogLevel::Debug, message.into())
+    }
+
+    /// Creates a new log entry of level `Info`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_info("net", "Connected");
+    /// assert_eq!(log, Log::new(String::from("net"), LogLevel::Info, String::from("Connected")));
+    /// ```
+    pub fn new_info(channel: impl Into<String>, message: impl Into<String>) -> Log {
+        Log::new(channel.into(), LogLevel::Info, message.into())
+    }
+
+    /// Creates a new log entry of level `Warn`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_warn("net", "Retrying");
+    /// assert_eq!(log, Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying")));
+    /// ```
+    pub fn new_warn(channel: impl Into<String>, message: impl Into<String>) -> Log {
+        Log::new(channel.into(), LogLevel::Warn, message.into())
+    }
+
+    /// Creates a new log entry of level `Error`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_error("net", "Connection lost");
+    /// assert_eq!(log, Log::new(String::from("net"), LogLevel::Error, String::from("Connection lost")));
+    /// ```
+    pub fn new_error(channel: impl Into<String>, message: impl Into<String>) -> Log {
+        Log::new(channel.into(), LogLevel::Error, message.into())
+    }
+
+    /// Creates a new log entry of level `Critical`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_critical("net", "Watchdog expired");
+    /// assert_eq!(log, Log::new(String::from("net"), LogLevel::Critical, String::from("Watchdog expired")));
+    /// ```
+    pub fn new_critical(channel: impl Into<String>, message: impl Into<String>) -> Log {
+        Log::new(channel.into(), LogLevel::Critical, message.into())
+    }
+
+    /// Creates a new log entry that belongs to several channels, the first of which becomes its `channel`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_multi(vec![String::from("network"), String::from("security")], LogLevel::Warn, String::from("Port scan detected"));
+    /// assert_eq!(format!("{}", log), "[network,security/Warn    ] Port scan detected");
+    /// assert!(log.in_channel("security"));
+    /// ```
+    pub fn new_multi(channels: Vec<String>, level: LogLevel, message: String) -> Log {
+        let mut channels = channels.into_iter();
+        let channel = channels.next().unwrap_or_default();
+        Log {
+            extra_channels: channels.collect(),
+            ..Log::new(channel, level, message)
+        }
+    }
+
+    /// Returns every channel the log belongs to, starting with `channel`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_multi(vec![String::from("network"), String::from("security")], LogLevel::Warn, String::from("Port scan detected"));
+    /// assert_eq!(log.channels().collect::<Vec<&str>>(), vec!["network", "security"]);
+    /// ```
+    pub fn channels(&self) -> impl Iterator<Item = &str> {
+        core::iter::once(self.channel.as_str()).chain(self.extra_channels.iter().map(String::as_str))
+    }
+
+    /// Checks if the log belongs to the given channel, either as its `channel` or one of its extra channels.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new(String::from("net"), LogLevel::Info, String::from("Connected"));
+    /// assert!(log.in_channel("net"));
+    /// assert!(!log.in_channel("disk"));
+    /// ```
+    pub fn in_channel(&self, channel: &str) -> bool {
+        self.channels().any(|own| own == channel)
+    }
+
+    /// Creates a new log entry with structured key-value metadata.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_with_meta(String::from("net"), LogLevel::Error, String::from("connect failed"), &[("host", "192.168.1.1"), ("port", "443")]);
+    /// assert_eq!(format!("{}", log), "[net/Error   ] connect failed host=192.168.1.1 port=443");
+    /// ```
+    pub fn new_with_meta(channel: String, level: LogLevel, message: String, meta: &[(&str, &str)]) -> Log {
+        Log {
+            metadata: Some(meta.iter().map(|(key, value)| (String::from(*key), String::from(*value))).collect()),
+            ..Log::new(channel, level, message)
+        }
+    }
+
+    /// Replaces every control character other than tab and newline, and every character outside printable ASCII,
+    /// with `'?'` in the message and channels, so that logs built from external input cannot corrupt a terminal.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let mut log = Log::new(String::from("net\0"), LogLevel::Warn, String::from("bad packet: \x1b[2J\tdone"));
+    /// log.sanitize();
+    /// assert_eq!(log.channel, "net?");
+    /// assert_eq!(log.message, "bad packet: ?[2J\tdone");
+    /// ```
+    pub fn sanitize(&mut self) {
+        fn sanitize_str(s: &mut String) {
+            if s.chars().any(|c| !is_printable(c)) {
+                *s = s.chars().map(|c| if is_printable(c) { c } else { '?' }).collect();
+            }
+        }
+        fn is_printable(c: char) -> bool {
+            c == '\t' || c == '\n' || (' '..='~').contains(&c)
+        }
+        sanitize_str(&mut self.message);
+        sanitize_str(&mut self.channel);
+        for channel in &mut self.extra_channels {
+            sanitize_str(channel);
+        }
+    }
+
+    /// Returns whether the log was made without a channel, i.e. its channel is `NO_CHANNEL`.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel, NO_CHANNEL};
+    /// assert!(Log::new(String::from(NO_CHANNEL), LogLevel::Info, String::from("Test log message")).is_empty_channel());
+    /// assert!(!Log::new(String::from("net"), LogLevel::Info, String::from("Test log message")).is_empty_channel());
+    /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn is_empty_channel(&self) -> bool {
+        self.channel.is_empty()
+    }
+
+    /// Returns a key that compares and hashes the log by its channel, level and message only,
+    /// ignoring its sequence number, timestamp and other bookkeeping.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let first = Log::new(String::from("net"), LogLevel::Error, String::from("connect failed"));
+    /// let mut second = first.clone();
+    /// second.sequence = 42;
+    /// assert_ne!(first, second);
+    /// assert_eq!(first.key(), second.key());
+    /// ```
+    pub fn key(&self) -> LogKey {
+        LogKey(self.clone())
+    }
+
+    /// Returns how many ticks ago the log was made, according to the clock registered by `set_clock`.
+    /// Returns `None` if no clock is registered or the log has no timestamp.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel, set_clock, clear_clock};
+    /// fn ticks() -> u64 {
+    ///     1000
+    /// }
+    ///
+    /// let mut log = Log::new(String::from("net"), LogLevel::Info, String::from("Connected"));
+    /// log.timestamp = Some(400);
+    /// set_clock(ticks);
+    /// assert_eq!(log.age_ticks(), Some(600));
+    /// clear_clock();
+    /// assert_eq!(log.age_ticks(), None);
+    /// ```
+    #[cfg(not(feature = "no_global"))]
+    pub fn age_ticks(&self) -> Option<u64> {
+        let now = read_clock()?;
+        Some(now.saturating_sub(self.timestamp?))
+    }
+
+    /// Checks if the log is at least as severe as the minimum log level and is in one of the channels,
+    /// using the same filtering as the traceback functions. `None` matches every level or channel.
+    /// ```rust
+    /// use breadcrumbs::{LevelFilter, Log, LogLevel};
+    /// let log = Log::new(String::from("net"), LogLevel::Error, String::from("connect failed"));
+    /// assert!(log.matches_filter(Some(LogLevel::Warn), Some(&["net", "disk"])));
+    /// assert!(log.matches_filter(None, None));
+    /// assert!(!log.matches_filter(Some(LogLevel::Critical), None));
+    /// assert!(!log.matches_filter(None, Some(&["disk"])));
+    /// assert!(!log.matches_filter(LevelFilter::None, None));
+    /// ```
+    pub fn matches_filter(&self, min_level: impl Into<LevelFilter>, channels: Option<&[&str]>) -> bool {
+        let level_matches = min_level.into().matches(self.level);
+        let channel_matches = channels.is_none() || channels.unwrap().iter().any(|channel| self.in_channel(channel));
+        level_matches && channel_matches
+    }
+
+    /// Checks if the log's metadata contains the given key-value pair.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new_with_meta(String::from("net"), LogLevel::Error, String::from("connect failed"), &[("port", "443")]);
+    /// assert!(log.has_meta("port", "443"));
+    /// assert!(!log.has_meta("port", "80"));
+    /// ```
+    pub fn has_meta(&self, key: &str, value: &str) -> bool {
+        match &self.metadata {
+            Some(metadata) => metadata.iter().any(|(k, v)| k == key && v == value),
+            None => false,
+        }
+    }
+
+    /// Returns a copy of the log with a key-value pair appended to its metadata, for attaching context in a chain.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::new(String::from("http"), LogLevel::Warn, String::from("slow request"))
+    ///     .with_context("request_id", "abc123")
+    ///     .with_context("user", "bob");
+    /// assert_eq!(format!("{}", log), "[http/Warn    ] slow request request_id=abc123 user=bob");
+    /// ```
+    pub fn with_context(&self, key: &str, value: &str) -> Log {
+        let mut log = self.clone();
+        log.add_context(key, value);
+        log
+    }
+
+    /// Appends a key-value pair to the log's metadata in place.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let mut log = Log::new(String::from("http"), LogLevel::Warn, String::from("slow request"));
+    /// log.add_context("request_id", "abc123");
+    /// assert!(log.has_meta("request_id", "abc123"));
+    /// ```
+    pub fn add_context(&mut self, key: &str, value: &str) {
+        self.metadata.get_or_insert_with(Vec::new).push((String::from(key), String::from(value)));
+    }
+
+    /// Creates a `LogBuilder` for constructing a log entry with a fluent API.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::builder().message("Test log message").build();
+    /// assert_eq!(log.channel, "");
+    /// assert_eq!(log.level, LogLevel::Info);
+    /// ```
+    pub fn builder() -> LogBuilder {
+        LogBuilder::default()
+    }
+
+    /// Creates a new log entry with the source-code location it originated from.
+    /// ```rust
+    /// use breadcrumbs::{Log, LogLevel};
+    /// let log = Log::with_location(String::from("test_channel"), LogLevel::Info, String::from("Test log message"), "src/main.rs", 42, 5);
+    /// assert_eq!(format!("{}", log), "[test_channel/Info    ] Test log message @ src/main.rs:42");
+    /// ```
+    pub fn with_location(channel: String, level: LogLevel, message: String, file: &'static str, line: u32, column: u32) -> Log {
+        Log {
+            file: Some(file),
+            line: Some(line),
+            column: Some(column),
+            ..Log::new(channel, level, message)
+        }
+    }
+
+    /// Removes the log from the stored traceback of logs.
+    /// This log will not use up memory or be printed by the traceback macros.
+    /// Useful in embedded systems where memory is limited.
+    /// 
+    /// # Example Usecase
+    /// ```rust
+    /// use breadcrumbs::{LogListener, Log};
+    /// 
+    /// struct MyLogListener;
+    /// impl LogListener for MyLogListener {
+    ///     fn on_log(&mut self, log: Log) {
+    ///         if !log.level.is_at_least(breadcrumbs::LogLevel::Warn) {
+    ///             log.remove();
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Does nothing if the log is not in the stored traceback, e.g. because it was already removed.
+    /// Use `try_remove` to find out whether the log was removed.
+    #[cfg(not(feature = "no_global"))]
+    pub fn remove(&self) {
+        let _ = self.try_remove();
+    }
+
+    /// Removes the log from the stored traceback of logs, returning an error if it is not present.
+    /// This never panics, so it is safe to call when the log may have already been removed.
+    /// The stored log is found by its channel, level and message, preferring an exact match so that
+    /// the right one of several identical logs is removed, and so that a log whose occurrence count has
+    /// since been increased by deduplication can still be removed.
+    /// ```rust
+    /// use breadcrumbs::{init, log, traceback, LogNotFound};
+    /// init!();
+    /// log!("Test log message");
+    /// let log = traceback!().0[0].clone();
+    /// assert_eq!(log.try_remove(), Ok(()));
+    /// assert_eq!(log.try_remove(), Err(LogNotFound));
+    /// ```
+    #[cfg(not(feature = "no_global"))]
+    pub fn try_remove(&self) -> Result<(), LogNotFound> {
+        let removed = LOGS.with(|logs| {
+            let key = self.key();
+            let index = logs.iter().position(|log| log == self)
+                .or_else(|| logs.iter().position(|log| key.matches(log)))?;
+            let removed = logs.iter().nth(index).and_then(try_clone_log);
+            logs.remove_at(index);
+            LOG_COUNT.store(logs.len(), Ordering::Relaxed);
+            Some(removed)
+        }).ok_or(LogNotFound)?;
+        PENDING_REMOVALS.with(|pending| match removed {
+            Some(removed) if pending.try_reserve(1).is_ok() => pending.push(removed),
+            _ => note_out_of_memory(),
+        });
+        notify_pending_removals();
+        report_out_of_memory();
+        Ok(())
+    }
+}
+
+/// A wrapper around a `Log` that compares and hashes it by its channel, level and message only.
+/// Two logs with the same content but different sequence numbers or timestamps have equal keys,
+/// which makes `LogKey` suitable for deduplication and set-based operations.
+/// ```rust
+/// use breadcrumbs::{Log, LogKey, LogLevel};
+/// let mut keys: Vec<LogKey> = Vec::new();
+/// for sequence in 1..=3 {
+///     let mut log = Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying"));
+///     log.sequence = sequence;
+///     if !keys.contains(&log.key()) {
+///         keys.push(log.key());
+///     }
+/// }
+/// assert_eq!(keys.len(), 1);
 /// ```
-#[macro_export]
-macro_rules! traceback_level {
-    ($arg1:expr) => {
-        $crate::get_logs_traceback(Some($arg1), None)
-    };
+#[derive(Clone, Debug)]
+pub struct LogKey(pub Log);
+
+impl LogKey {
+    fn matches(&self, log: &Log) -> bool {
+        self.0.channel == log.channel && self.0.extra_channels == log.extra_channels && self.0.level == log.level && self.0.message == log.message
+    }
+}
+
+impl PartialEq for LogKey {
+    fn eq(&self, other: &LogKey) -> bool {
+        self.matches(&other.0)
+    }
+}
+
+impl Eq for LogKey {}
+
+impl core::hash::Hash for LogKey {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.0.channel.hash(state);
+        self.0.extra_channels.hash(state);
+        self.0.level.hash(state);
+        self.0.message.hash(state);
+    }
+}
+
+impl From<Log> for (String, LogLevel, String) {
+    fn from(log: Log) -> (String, LogLevel, String) {
+        (log.channel, log.level, log.message)
+    }
+}
+
+impl From<(String, LogLevel, String)> for Log {
+    fn from((channel, level, message): (String, LogLevel, String)) -> Log {
+        Log::new(channel, level, message)
+    }
+}
+
+impl From<&Log> for String {
+    fn from(log: &Log) -> String {
+        log.to_string()
+    }
+}
+
+/// Parses a log from its `[channel/Level] message` display format.
+/// The channel may be omitted, as in `[Level] message`, or be several comma-separated channels,
+/// and the level may be padded with trailing spaces.
+/// ```rust
+/// use breadcrumbs::{Log, LogLevel, ParseLogError};
+/// let log = Log::try_from("[net/Error] connect failed").unwrap();
+/// assert_eq!(log, Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")));
+/// assert_eq!(Log::try_from("net/Error connect failed"), Err(ParseLogError::MissingBracket));
+/// ```
+impl TryFrom<&str> for Log {
+    type Error = ParseLogError;
+
+    fn try_from(s: &str) -> Result<Log, ParseLogError> {
+        let rest = s.strip_prefix('[').ok_or(ParseLogError::MissingBracket)?;
+        let (header, message) = rest.split_once(']').ok_or(ParseLogError::MissingBracket)?;
+        let message = message.strip_prefix(' ').unwrap_or(message);
+        let (channel, level) = match header.rsplit_once('/') {
+            Some((channel, level)) => (channel, LogLevel::try_from_str(level.trim_end()).map_err(|_| ParseLogError::UnknownLevel)?),
+            None => ("", LogLevel::try_from_str(header.trim_end()).map_err(|_| ParseLogError::MissingSlash)?),
+        };
+        if message.is_empty() {
+            return Err(ParseLogError::EmptyMessage);
+        }
+        Ok(Log::new_multi(channel.split(',').map(String::from).collect(), level, String::from(message)))
+    }
+}
+
+/// The error returned when parsing a `Log` from its display format fails.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ParseLogError {
+    /// The log does not start with a bracketed `[channel/Level]` header.
+    MissingBracket,
+    /// The header has no `/` separating the channel from the level, and is not a level on its own.
+    MissingSlash,
+    /// The level in the header is not a known `LogLevel`.
+    UnknownLevel,
+    /// There is no message after the header.
+    EmptyMessage,
+}
+
+impl core::fmt::Display for ParseLogError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let error_str = match self {
+            ParseLogError::MissingBracket => "missing bracketed header",
+            ParseLogError::MissingSlash => "missing slash between channel and level",
+            ParseLogError::UnknownLevel => "unknown log level",
+            ParseLogError::EmptyMessage => "empty log message",
+        };
+        write!(f, "{}", error_str)
+    }
+}
+
+/// A fluent builder for `Log` entries, created with `Log::builder()`.
+/// The channel defaults to an empty string and the level to `LogLevel::Info`.
+/// ```rust
+/// use breadcrumbs::{Log, LogLevel};
+/// let log = Log::builder().channel("net").level(LogLevel::Error).message("connect failed").build();
+/// assert_eq!(log, Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LogBuilder {
+    channel: String,
+    level: LogLevel,
+    message: String,
+}
+
+impl LogBuilder {
+    /// Sets the channel of the log.
+    pub fn channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = channel.into();
+        self
+    }
+
+    /// Sets the level of the log.
+    pub fn level(mut self, level: LogLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the message of the log.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// Builds the log entry.
+    pub fn build(self) -> Log {
+        Log::new(self.channel, self.level, self.message)
+    }
+}
+
+/// The error returned by `Log::try_remove` when the log is not in the stored traceback.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct LogNotFound;
+
+impl core::fmt::Display for LogNotFound {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "log not found in the stored traceback")
+    }
+}
+
+/// A trait for handling log entries.
+/// It is implemented for any `FnMut(Log)` closure, so a closure can be used as a listener directly.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::init_with_listener;
+/// init_with_listener(Box::new(|log| println!("{}", log)));
+/// # }
+/// ```
+pub trait LogListener: Send + Sync {
+    fn on_log(&mut self, log: Log);
+
+    /// Called after the log store has been cleared by `clear_logs()` or `init()`.
+    /// Neither the log store nor the listener list is locked while this is called, so it is safe to log from inside `on_flush`.
+    /// Logs made while `on_flush` runs are passed to this listener once it returns.
+    fn on_flush(&mut self) {}
+
+    /// Called once the logging system has been initialized by `init()`, `init_with_listener()` or `init_append()`,
+    /// after all state changes. Useful for setting up resources or resetting statistics.
+    /// Neither the log store nor the listener list is locked while this is called, so it is safe to log from inside `on_init`.
+    /// Logs made while `on_init` runs are passed to this listener once it returns.
+    fn on_init(&mut self) {}
+
+    /// Called after a log has been removed from the log store by `Log::remove()` or `Log::try_remove()`.
+    /// The log store is not locked while this is called. Removals made from inside a listener callback,
+    /// such as `on_log`, are reported once that callback has returned.
+    fn on_remove(&mut self, _log: &Log) {}
+
+    /// Called before `on_log` for every new log; returning `false` skips `on_log` for that log.
+    /// The log is still stored, so a listener can filter what it receives without a wrapping `FilterListener`.
+    /// ```rust
+    /// # #[cfg(not(feature = "no_global"))]
+    /// # {
+    /// use breadcrumbs::{add_listener, log, Log, LogLevel, LogListener};
+    /// struct ErrorListener;
+    ///
+    /// impl LogListener for ErrorListener {
+    ///     fn on_log(&mut self, log: Log) {
+    ///         println!("{}", log);
+    ///     }
+    ///
+    ///     fn filter_hook(&self, log: &Log) -> bool {
+    ///         log.level.is_at_least(LogLevel::Error)
+    ///     }
+    /// }
+    ///
+    /// add_listener!(ErrorListener);
+    /// log!(LogLevel::Info, "net", "Connected");
+    /// # }
+    /// ```
+    fn filter_hook(&self, _log: &Log) -> bool {
+        true
+    }
+}
+
+impl<F: FnMut(Log) + Send + Sync> LogListener for F {
+    fn on_log(&mut self, log: Log) {
+        self(log)
+    }
+}
+
+/// A listener that buffers logs instead of passing them on immediately, so that a slow inner listener,
+/// e.g. one that does I/O, can be run later by calling `flush()` outside of the logging path.
+/// Clones share the same buffer and inner listener, so register a clone and keep the original to flush it.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{add_listener, log, BatchListener, Log};
+/// let mut batch = BatchListener::new(|log: Log| println!("{}", log));
+/// add_listener!(batch.clone());
+/// log!("Test log message");
+/// batch.flush();
+/// # }
+/// ```
+pub struct BatchListener<L: LogListener> {
+    buffer: Arc<Mutex<Vec<Log>>>,
+    inner: Arc<Mutex<L>>,
+}
+
+impl<L: LogListener> BatchListener<L> {
+    /// Creates a batching listener that passes buffered logs to `inner` when flushed.
+    /// ```rust
+    /// use breadcrumbs::{BatchListener, Log};
+    /// let batch = BatchListener::new(|log: Log| println!("{}", log));
+    /// ```
+    pub fn new(inner: L) -> BatchListener<L> {
+        BatchListener {
+            buffer: Arc::new(Mutex::new(Vec::new())),
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Passes every buffered log to the inner listener, in the order they were logged, and empties the buffer.
+    /// The buffer is only locked while it is taken, so logs can keep arriving while the inner listener runs.
+    /// ```rust
+    /// use breadcrumbs::{BatchListener, Log, LogLevel, LogListener};
+    /// let mut batch = BatchListener::new(|log: Log| println!("{}", log));
+    /// batch.on_log(Log::new(String::from("net"), LogLevel::Info, String::from("Connected")));
+    /// batch.flush();
+    /// ```
+    pub fn flush(&mut self) {
+        let logs = core::mem::take(&mut *self.buffer.lock());
+        let mut inner = self.inner.lock();
+        for log in logs {
+            if inner.filter_hook(&log) {
+                inner.on_log(log);
+            }
+        }
+    }
+}
+
+impl<L: LogListener> Clone for BatchListener<L> {
+    fn clone(&self) -> BatchListener<L> {
+        BatchListener {
+            buffer: self.buffer.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<L: LogListener> LogListener for BatchListener<L> {
+    fn on_log(&mut self, log: Log) {
+        self.buffer.lock().push(log);
+    }
 }
 
-/// A macro for generating a `Traceback` of logs given only a channel.
-/// 
-/// # Examples
-/// 
-/// Basic usage:
-/// 
-/// ```
-/// use breadcrumbs::traceback_channel;
-/// let traceback = traceback_channel!("test_channel");
-/// ```
-#[macro_export]
-macro_rules! traceback_channel {
-    ($arg1:expr) => {
-        $crate::get_logs_traceback(None, Some(vec![$arg1.to_string()]))
-    };
-}
+/// A listener that passes every log to two inner listeners, first `A` and then `B`.
+/// Nest tees to combine more listeners, e.g. `TeeListener::new(a, TeeListener::new(b, c))`.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{init, log, Log, TeeListener};
+/// let tee = TeeListener::new(|log: Log| println!("UART: {}", log), |log: Log| println!("USB: {}", log));
+/// init!(tee);
+/// log!("Test log message");
+/// # }
+/// ```
+pub struct TeeListener<A: LogListener, B: LogListener> {
+    first: A,
+    second: B,
+}
+
+impl<A: LogListener, B: LogListener> TeeListener<A, B> {
+    /// Creates a listener that passes every log to `a` and then to `b`.
+    /// ```rust
+    /// use breadcrumbs::{Log, TeeListener};
+    /// let tee = TeeListener::new(|log: Log| println!("{}", log), |log: Log| println!("{}", log));
+    /// ```
+    pub fn new(a: A, b: B) -> TeeListener<A, B> {
+        TeeListener { first: a, second: b }
+    }
+}
+
+impl<A: LogListener, B: LogListener> LogListener for TeeListener<A, B> {
+    fn on_log(&mut self, log: Log) {
+        if self.first.filter_hook(&log) {
+            self.first.on_log(log.clone());
+        }
+        if self.second.filter_hook(&log) {
+            self.second.on_log(log);
+        }
+    }
+
+    fn on_flush(&mut self) {
+        self.first.on_flush();
+        self.second.on_flush();
+    }
+
+    fn on_init(&mut self) {
+        self.first.on_init();
+        self.second.on_init();
+    }
+
+    fn on_remove(&mut self, log: &Log) {
+        self.first.on_remove(log);
+        self.second.on_remove(log);
+    }
+}
+
+/// A listener that passes only the logs matching a predicate on to an inner listener, dropping the rest.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{add_listener, log, FilterListener, Log, LogLevel};
+/// let uart = FilterListener::new(|log: Log| println!("UART: {}", log), |log: &Log| log.level.is_at_least(LogLevel::Error));
+/// add_listener!(uart);
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// # }
+/// ```
+pub struct FilterListener<L: LogListener, F: Fn(&Log) -> bool + Send + Sync> {
+    inner: L,
+    predicate: F,
+}
+
+impl<L: LogListener, F: Fn(&Log) -> bool + Send + Sync> FilterListener<L, F> {
+    /// Creates a listener that passes the logs for which `predicate` returns `true` on to `inner`.
+    /// ```rust
+    /// use breadcrumbs::{FilterListener, Log};
+    /// let filter = FilterListener::new(|log: Log| println!("{}", log), |log: &Log| log.channel == "net");
+    /// ```
+    pub fn new(inner: L, predicate: F) -> FilterListener<L, F> {
+        FilterListener { inner, predicate }
+    }
+}
+
+impl<L: LogListener, F: Fn(&Log) -> bool + Send + Sync> LogListener for FilterListener<L, F> {
+    fn on_log(&mut self, log: Log) {
+        if (self.predicate)(&log) && self.inner.filter_hook(&log) {
+            self.inner.on_log(log);
+        }
+    }
+
+    fn on_flush(&mut self) {
+        self.inner.on_flush();
+    }
+
+    fn on_init(&mut self) {
+        self.inner.on_init();
+    }
+
+    fn on_remove(&mut self, log: &Log) {
+        if (self.predicate)(log) {
+            self.inner.on_remove(log);
+        }
+    }
+}
+
+/// A listener that counts the logs it receives per level and per channel, without storing them.
+/// Register it wrapped in an `Arc<Mutex<CountingListener>>` and keep a clone of that to query the counts.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{add_listener, log, CountingListener, LogLevel};
+/// use std::sync::Arc;
+/// use spin::Mutex;
+/// let counter = Arc::new(Mutex::new(CountingListener::new()));
+/// add_listener!(counter.clone());
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// assert!(counter.lock().error_count() >= 1);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CountingListener {
+    level_counts: [u64; ALL_LOG_LEVELS.len()],
+    channel_counts: BTreeMap<String, u64>,
+}
+
+impl CountingListener {
+    /// Creates a listener with all counts at zero.
+    /// ```rust
+    /// use breadcrumbs::{CountingListener, LogLevel};
+    /// let counter = CountingListener::new();
+    /// assert_eq!(counter.count_for_level(LogLevel::Info), 0);
+    /// ```
+    pub fn new() -> CountingListener {
+        CountingListener::default()
+    }
+
+    /// Returns the number of `Error` logs received since the last reset.
+    /// ```rust
+    /// use breadcrumbs::{CountingListener, Log, LogLevel, LogListener};
+    /// let mut counter = CountingListener::new();
+    /// counter.on_log(Log::new(String::from("motor"), LogLevel::Error, String::from("Stalled")));
+    /// assert_eq!(counter.error_count(), 1);
+    /// ```
+    pub fn error_count(&self) -> u64 {
+        self.count_for_level(LogLevel::Error)
+    }
+
+    /// Returns the number of logs of exactly the given level received since the last reset.
+    /// ```rust
+    /// use breadcrumbs::{CountingListener, Log, LogLevel, LogListener};
+    /// let mut counter = CountingListener::new();
+    /// counter.on_log(Log::new(String::from("motor"), LogLevel::Warn, String::from("Hot")));
+    /// assert_eq!(counter.count_for_level(LogLevel::Warn), 1);
+    /// assert_eq!(counter.count_for_level(LogLevel::Error), 0);
+    /// ```
+    pub fn count_for_level(&self, level: LogLevel) -> u64 {
+        self.level_counts[level.numeric() as usize]
+    }
+
+    /// Returns the number of logs on the given channel received since the last reset.
+    /// A log in several channels counts once for each of them.
+    /// ```rust
+    /// use breadcrumbs::{CountingListener, Log, LogLevel, LogListener};
+    /// let mut counter = CountingListener::new();
+    /// counter.on_log(Log::new(String::from("motor"), LogLevel::Warn, String::from("Hot")));
+    /// assert_eq!(counter.count_for_channel("motor"), 1);
+    /// assert_eq!(counter.count_for_channel("net"), 0);
+    /// ```
+    pub fn count_for_channel(&self, channel: &str) -> u64 {
+        self.channel_counts.get(channel).copied().unwrap_or(0)
+    }
+
+    /// Sets all counts back to zero.
+    /// ```rust
+    /// use breadcrumbs::{CountingListener, Log, LogLevel, LogListener};
+    /// let mut counter = CountingListener::new();
+    /// counter.on_log(Log::new(String::from("motor"), LogLevel::Error, String::from("Stalled")));
+    /// counter.reset_counts();
+    /// assert_eq!(counter.error_count(), 0);
+    /// assert_eq!(counter.count_for_channel("motor"), 0);
+    /// ```
+    pub fn reset_counts(&mut self) {
+        self.level_counts = [0; ALL_LOG_LEVELS.len()];
+        self.channel_counts.clear();
+    }
+}
+
+impl LogListener for CountingListener {
+    fn on_log(&mut self, log: Log) {
+        self.level_counts[log.level.numeric() as usize] += 1;
+        for channel in log.channels() {
+            match self.channel_counts.get_mut(channel) {
+                Some(count) => *count += 1,
+                None => {
+                    self.channel_counts.insert(String::from(channel), 1);
+                }
+            }
+        }
+    }
+}
+
+impl LogListener for Arc<Mutex<CountingListener>> {
+    fn on_log(&mut self, log: Log) {
+        self.lock().on_log(log);
+    }
+}
+
+/// A guard that logs `"→ name"` at `LogLevel::Verbose` when a scope is entered and `"← name"` when it is dropped,
+/// for tracing call chains. Both logs carry the location `ScopedLog::enter` was called from.
+/// Note that the `scope_log!` macro is the preferred method to create one in the public API.
+/// ```rust
+/// use breadcrumbs::{traceback_channel, ScopedLog};
+/// {
+///     let _guard = ScopedLog::enter("motor", "calibrate");
+/// }
+/// let traceback = traceback_channel!("motor");
+/// assert_eq!(traceback.0[0].message, "→ calibrate");
+/// assert_eq!(traceback.0[1].message, "← calibrate");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub struct ScopedLog {
+    channel: String,
+    name: String,
+    location: &'static core::panic::Location<'static>,
+}
+
+#[cfg(not(feature = "no_global"))]
+impl ScopedLog {
+    /// Logs entry into the scope called `name` and returns a guard that logs the exit when dropped.
+    /// ```rust
+    /// use breadcrumbs::ScopedLog;
+    /// let guard = ScopedLog::enter("motor", "calibrate");
+    /// drop(guard);
+    /// ```
+    #[track_caller]
+    pub fn enter(channel: &str, name: &str) -> ScopedLog {
+        let scope = ScopedLog {
+            channel: String::from(channel),
+            name: String::from(name),
+            location: core::panic::Location::caller(),
+        };
+        scope.log('→');
+        scope
+    }
+
+    fn log(&self, arrow: char) {
+        if _level_enabled(LogLevel::Verbose) {
+            let message = format!("{} {}", arrow, self.name);
+            _log_with_location(Some(LogLevel::Verbose), Some(&self.channel), message, self.location.file(), self.location.line(), self.location.column());
+        }
+    }
+}
+
+#[cfg(not(feature = "no_global"))]
+impl Drop for ScopedLog {
+    fn drop(&mut self) {
+        self.log('←');
+    }
+}
+
+/// A listener that queues logs for an inner listener run by an async task, so that a slow listener does not block logging.
+/// `on_log` only pushes the log onto a bounded queue and wakes the task; logs arriving while the queue is full are dropped
+/// and counted, and the count can be read with `AsyncListenerDriver::dropped_count`.
+/// The driver future returned alongside it works with any executor, such as Embassy or Tokio, and completes
+/// once the `AsyncListener` has been dropped, e.g. by `remove_listener`, and the queue is empty.
+/// Only `on_log` is passed on to the inner listener.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{add_listener, log, AsyncListener, Log};
+/// let (listener, driver) = AsyncListener::new(|log: Log| println!("{}", log), 32);
+/// add_listener!(listener);
+/// log!("Test log message");
+/// // spawn `driver` on the executor, e.g. `tokio::spawn(driver)`
+/// # drop(driver);
+/// # }
+/// ```
+pub struct AsyncListener<L: LogListener> {
+    queue: Arc<Mutex<AsyncQueue>>,
+    _inner: core::marker::PhantomData<fn(L)>,
+}
+
+struct AsyncQueue {
+    logs: VecDeque<Log>,
+    capacity: usize,
+    dropped: u64,
+    waker: Option<core::task::Waker>,
+    closed: bool,
+}
+
+/// The task that passes the logs queued by an `AsyncListener` on to its inner listener.
+/// It is a future that should be spawned on an executor.
+pub struct AsyncListenerDriver<L: LogListener> {
+    queue: Arc<Mutex<AsyncQueue>>,
+    inner: L,
+}
+
+impl<L: LogListener> AsyncListener<L> {
+    /// Creates a listener that queues up to `capacity` logs for `inner`, along with the driver future that runs `inner`.
+    /// ```rust
+    /// use breadcrumbs::{AsyncListener, Log};
+    /// let (listener, driver) = AsyncListener::new(|log: Log| println!("{}", log), 32);
+    /// ```
+    pub fn new(inner: L, capacity: usize) -> (AsyncListener<L>, AsyncListenerDriver<L>) {
+        let queue = Arc::new(Mutex::new(AsyncQueue {
+            logs: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+            waker: None,
+            closed: false,
+        }));
+        let listener = AsyncListener {
+            queue: queue.clone(),
+            _inner: core::marker::PhantomData,
+        };
+        (listener, AsyncListenerDriver { queue, inner })
+    }
+}
+
+impl<L: LogListener> LogListener for AsyncListener<L> {
+    fn on_log(&mut self, log: Log) {
+        let mut queue = self.queue.lock();
+        if queue.logs.len() < queue.capacity {
+            queue.logs.push_back(log);
+        } else {
+            queue.dropped = queue.dropped.saturating_add(1);
+        }
+        let waker = queue.waker.take();
+        drop(queue);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<L: LogListener> Drop for AsyncListener<L> {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock();
+        queue.closed = true;
+        let waker = queue.waker.take();
+        drop(queue);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<L: LogListener> AsyncListenerDriver<L> {
+    /// Returns how many logs the `AsyncListener` has dropped because its queue was full.
+    /// ```rust
+    /// use breadcrumbs::{AsyncListener, Log};
+    /// let (listener, driver) = AsyncListener::new(|log: Log| println!("{}", log), 32);
+    /// assert_eq!(driver.dropped_count(), 0);
+    /// ```
+    pub fn dropped_count(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+}
+
+impl<L: LogListener + Unpin> core::future::Future for AsyncListenerDriver<L> {
+    type Output = ();
+
+    fn poll(self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<()> {
+        let driver = self.get_mut();
+        loop {
+            let mut queue = driver.queue.lock();
+            match queue.logs.pop_front() {
+                Some(log) => {
+                    drop(queue);
+                    driver.inner.on_log(log);
+                }
+                None if queue.closed => return core::task::Poll::Ready(()),
+                None => {
+                    queue.waker = Some(cx.waker().clone());
+                    return core::task::Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// A byte sink that a `PersistListener` writes its records to, such as a flash or serial driver.
+/// It mirrors the `write_all` method of `embedded_io::Write`, so a driver implementing that trait only needs a thin adapter.
+/// ```rust
+/// use breadcrumbs::PersistWrite;
+/// struct Flash;
+///
+/// impl PersistWrite for Flash {
+///     type Error = ();
+///
+///     fn write_all(&mut self, buf: &[u8]) -> Result<(), ()> {
+///         // program `buf` into the next free flash page
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait PersistWrite {
+    type Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl PersistWrite for Vec<u8> {
+    type Error = core::convert::Infallible;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), core::convert::Infallible> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+const PERSIST_MAX_CHANNEL_BYTES: usize = 63;
+const PERSIST_MAX_MESSAGE_BYTES: usize = 255;
+
+/// A listener that writes every log to a `PersistWrite` target as a fixed-length binary record,
+/// so logs survive a reset on devices that can only append raw bytes to storage.
+/// Each record is a 1-byte numeric level, a 1-byte channel length, 63 bytes of channel,
+/// a 2-byte little-endian message length and `max_message_bytes` bytes of message, with unused bytes zeroed.
+/// Channels and messages that do not fit are truncated, and no heap allocation is made while writing a record.
+/// ```rust
+/// use breadcrumbs::{Log, LogLevel, LogListener, PersistListener};
+/// let mut persist = PersistListener::new(Vec::new(), 32);
+/// persist.on_log(Log::new(String::from("net"), LogLevel::Warn, String::from("Link down")));
+/// let bytes = persist.into_inner();
+/// assert_eq!(bytes.len(), PersistListener::<Vec<u8>>::record_len(32));
+/// assert_eq!(bytes[0], LogLevel::Warn.numeric());
+/// ```
+pub struct PersistListener<W: PersistWrite + Send + Sync> {
+    writer: W,
+    max_message_bytes: usize,
+    write_errors: u64,
+}
+
+impl<W: PersistWrite + Send + Sync> PersistListener<W> {
+    /// Creates a listener that writes records with room for `max_message_bytes` bytes of message to `writer`.
+    /// `max_message_bytes` is capped at 255.
+    /// ```rust
+    /// use breadcrumbs::PersistListener;
+    /// let persist = PersistListener::new(Vec::new(), 64);
+    /// ```
+    pub fn new(writer: W, max_message_bytes: usize) -> PersistListener<W> {
+        PersistListener {
+            writer,
+            max_message_bytes: max_message_bytes.min(PERSIST_MAX_MESSAGE_BYTES),
+            write_errors: 0,
+        }
+    }
+
+    /// Returns the length in bytes of every record written by a listener created with `max_message_bytes`.
+    /// ```rust
+    /// use breadcrumbs::PersistListener;
+    /// assert_eq!(PersistListener::<Vec<u8>>::record_len(64), 131);
+    /// ```
+    pub fn record_len(max_message_bytes: usize) -> usize {
+        4 + PERSIST_MAX_CHANNEL_BYTES + max_message_bytes.min(PERSIST_MAX_MESSAGE_BYTES)
+    }
+
+    /// Returns the number of records that could not be written because the writer returned an error.
+    /// ```rust
+    /// use breadcrumbs::PersistListener;
+    /// let persist = PersistListener::new(Vec::new(), 64);
+    /// assert_eq!(persist.write_error_count(), 0);
+    /// ```
+    pub fn write_error_count(&self) -> u64 {
+        self.write_errors
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes the listener and returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn persist_prefix(s: &str, max: usize) -> &[u8] {
+    if s.len() <= max {
+        return s.as_bytes();
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s.as_bytes()[..end]
+}
+
+impl<W: PersistWrite + Send + Sync> LogListener for PersistListener<W> {
+    fn on_log(&mut self, log: Log) {
+        let mut record = [0u8; 4 + PERSIST_MAX_CHANNEL_BYTES + PERSIST_MAX_MESSAGE_BYTES];
+        let channel = persist_prefix(&log.channel, PERSIST_MAX_CHANNEL_BYTES);
+        let message = persist_prefix(&log.message, self.max_message_bytes);
+        record[0] = log.level.numeric();
+        record[1] = channel.len() as u8;
+        record[2..2 + channel.len()].copy_from_slice(channel);
+        let message_start = 2 + PERSIST_MAX_CHANNEL_BYTES;
+        record[message_start..message_start + 2].copy_from_slice(&(message.len() as u16).to_le_bytes());
+        record[message_start + 2..message_start + 2 + message.len()].copy_from_slice(message);
+        let len = PersistListener::<W>::record_len(self.max_message_bytes);
+        if self.writer.write_all(&record[..len]).is_err() {
+            self.write_errors += 1;
+        }
+    }
+}
+
+/// A `core::fmt::Write` target that accumulates everything written to it into the message of a single log,
+/// for code that reports its state through repeated `write!` calls.
+/// The log is made when the writer is flushed, or when it is dropped if anything was written and it was not flushed.
+/// Note that the `log_write!` macro is the preferred method to create one in the public API.
+/// ```rust
+/// use breadcrumbs::{last_log, LogLevel, LogWriter};
+/// use core::fmt::Write;
+/// let mut writer = LogWriter::new(LogLevel::Error, "net");
+/// write!(writer, "errno={}", 104).unwrap();
+/// write!(writer, ", retries={}", 3).unwrap();
+/// writer.flush();
+/// assert_eq!(last_log!().unwrap().message, "errno=104, retries=3");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub struct LogWriter {
+    channel: String,
+    level: LogLevel,
+    buf: String,
+    location: &'static core::panic::Location<'static>,
+}
+
+#[cfg(not(feature = "no_global"))]
+impl LogWriter {
+    /// Creates an empty writer for a log of the given level and channel.
+    /// ```rust
+    /// use breadcrumbs::{LogLevel, LogWriter};
+    /// let writer = LogWriter::new(LogLevel::Info, "net");
+    /// ```
+    #[track_caller]
+    pub fn new(level: LogLevel, channel: &str) -> LogWriter {
+        LogWriter {
+            channel: String::from(channel),
+            level,
+            buf: String::new(),
+            location: core::panic::Location::caller(),
+        }
+    }
+
+    /// Logs everything written so far as one message, consuming the writer.
+    /// ```rust
+    /// use breadcrumbs::{LogLevel, LogWriter};
+    /// use core::fmt::Write;
+    /// let mut writer = LogWriter::new(LogLevel::Info, "net");
+    /// writer.write_str("Connected").unwrap();
+    /// writer.flush();
+    /// ```
+    pub fn flush(mut self) {
+        self.log();
+    }
+
+    fn log(&mut self) {
+        let message = core::mem::take(&mut self.buf);
+        if _level_enabled(self.level) {
+            _log_with_location(Some(self.level), Some(&self.channel), message, self.location.file(), self.location.line(), self.location.column());
+        }
+    }
+}
+
+#[cfg(not(feature = "no_global"))]
+impl core::fmt::Write for LogWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.buf.push_str(s);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "no_global"))]
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            self.log();
+        }
+    }
+}
+
+/// A backing store for logs, which can replace the default `VecLogStore` through `set_log_store()`.
+/// Implement this to keep logs in a fixed-size circular buffer, external flash or anywhere else.
+/// Logs must be kept in the order they were pushed.
+/// ```rust
+/// # #[cfg(not(feature = "no_global"))]
+/// # {
+/// use breadcrumbs::{set_log_store, Log, LogStore, VecLogStore};
+///
+/// struct WriteThroughStore(Vec<Log>);
+///
+/// impl LogStore for WriteThroughStore {
+///     fn push(&mut self, log: Log) {
+///         println!("{}", log);
+///         self.0.push(log);
+///     }
+///
+///     fn iter<'a>(&'a self) -> Box<dyn DoubleEndedIterator<Item = &'a Log> + 'a> {
+///         Box::new(self.0.iter())
+///     }
+///
+///     fn remove_at(&mut self, idx: usize) {
+///         self.0.remove(idx);
+///     }
+///
+///     fn clear(&mut self) {
+///         self.0.clear();
+///     }
+/// }
+///
+/// set_log_store(Box::new(WriteThroughStore(Vec::new())));
+/// set_log_store(Box::new(VecLogStore::new()));
+/// # }
+/// ```
+pub trait LogStore {
+    /// Appends a log to the end of the store.
+    fn push(&mut self, log: Log);
+
+    /// Returns an iterator over the stored logs, from oldest to newest, which can also be walked from newest to oldest.
+    fn iter<'a>(&'a self) -> Box<dyn DoubleEndedIterator<Item = &'a Log> + 'a>;
+
+    /// Removes the log at the given position, shifting all logs after it.
+    fn remove_at(&mut self, idx: usize);
+
+    /// Removes every log from the store.
+    fn clear(&mut self);
+
+    /// Returns the number of stored logs.
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if the store holds no logs.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the most recently pushed log.
+    fn last(&self) -> Option<&Log> {
+        self.iter().next_back()
+    }
+
+    /// Reserves room for at least `additional` more logs, if the store supports it.
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+/// The default log store, keeping logs in a `Vec`.
+/// ```rust
+/// use breadcrumbs::{Log, LogLevel, LogStore, VecLogStore};
+/// let mut store = VecLogStore::new();
+/// store.push(Log::new(String::from("test_channel"), LogLevel::Info, String::from("Test log message")));
+/// assert_eq!(store.len(), 1);
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct VecLogStore(pub Vec<Log>);
+
+impl VecLogStore {
+    /// Creates an empty store.
+    /// ```rust
+    /// use breadcrumbs::{LogStore, VecLogStore};
+    /// assert!(VecLogStore::new().is_empty());
+    /// ```
+    pub fn new() -> VecLogStore {
+        VecLogStore(Vec::new())
+    }
+
+    /// Creates an empty store with room for `capacity` logs.
+    /// ```rust
+    /// use breadcrumbs::VecLogStore;
+    /// assert!(VecLogStore::with_capacity(64).0.capacity() >= 64);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> VecLogStore {
+        VecLogStore(Vec::with_capacity(capacity))
+    }
+}
+
+impl LogStore for VecLogStore {
+    fn push(&mut self, log: Log) {
+        if self.0.try_reserve(1).is_err() {
+            #[cfg(not(feature = "no_global"))]
+            {
+                OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+                note_out_of_memory();
+            }
+            return;
+        }
+        self.0.push(log);
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn DoubleEndedIterator<Item = &'a Log> + 'a> {
+        Box::new(self.0.iter())
+    }
+
+    fn remove_at(&mut self, idx: usize) {
+        self.0.remove(idx);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn last(&self) -> Option<&Log> {
+        self.0.last()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.0.try_reserve(additional).is_err() {
+            #[cfg(not(feature = "no_global"))]
+            note_out_of_memory();
+        }
+    }
+}
+
+/// A bounded-count log store backed by a fixed-capacity `heapless::Vec`, holding at most `N` logs.
+/// The slots for the logs live inline, so the store itself never grows or reallocates, and once it is full the oldest
+/// log is discarded to make room for each new one. This is not heap-free: every stored log still owns its channel,
+/// message and metadata on the heap. Messages longer than `M` bytes are cut to their first `M` bytes followed by `"…"`,
+/// which bounds the size of those allocations rather than avoiding them.
+/// This requires the `heapless` feature.
+/// ```rust
+/// # #[cfg(all(feature = "heapless", not(feature = "no_global")))]
+/// # {
+/// use breadcrumbs::{set_log_store, log, traceback, BoundedLogStore, VecLogStore};
+/// set_log_store(Box::new(BoundedLogStore::<2>::new()));
+/// log!("Log 1");
+/// log!("Log 2");
+/// log!("Log 3");
+/// assert_eq!(traceback!().len(), 2);
+/// set_log_store(Box::new(VecLogStore::new()));
+/// # }
+/// ```
+#[cfg(feature = "heapless")]
+#[derive(Clone, Debug, Default)]
+pub struct BoundedLogStore<const N: usize = 64, const M: usize = 128>(heapless::Vec<Log, N>);
+
+#[cfg(feature = "heapless")]
+impl<const N: usize, const M: usize> BoundedLogStore<N, M> {
+    /// Creates an empty store.
+    pub fn new() -> BoundedLogStore<N, M> {
+        BoundedLogStore(heapless::Vec::new())
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize, const M: usize> LogStore for BoundedLogStore<N, M> {
+    fn push(&mut self, mut log: Log) {
+        if N == 0 {
+            return;
+        }
+        if self.0.is_full() {
+            self.0.remove(0);
+        }
+        truncate_message(&mut log.message, M);
+        let _ = self.0.push(log);
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn DoubleEndedIterator<Item = &'a Log> + 'a> {
+        Box::new(self.0.iter())
+    }
+
+    fn remove_at(&mut self, idx: usize) {
+        self.0.remove(idx);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn last(&self) -> Option<&Log> {
+        self.0.last()
+    }
+}
+
+/// A unique identifier for a registered `LogListener`.
+/// Returned by `add_listener` and used to unregister the listener with `remove_listener`.
+#[cfg(not(feature = "no_global"))]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub struct ListenerId(u64);
+
+#[cfg(not(feature = "no_global"))]
+impl ListenerId {
+    fn next() -> ListenerId {
+        ListenerId(NEXT_LISTENER_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// The lock guarding each piece of global state: a `spin::Mutex`, or a `critical_section::Mutex<RefCell<T>>`
+/// when the `critical_section` feature is enabled.
+/// The value is only reachable from inside a closure, so every critical section is entered and left by a single
+/// `critical_section::with` call.
+#[cfg(all(not(feature = "critical_section"), not(feature = "no_global")))]
+struct GlobalMutex<T>(Mutex<T>);
+
+#[cfg(all(feature = "critical_section", not(feature = "no_global")))]
+struct GlobalMutex<T>(critical_section::Mutex<core::cell::RefCell<T>>);
+
+#[cfg(all(not(feature = "critical_section"), not(feature = "no_global")))]
+impl<T> GlobalMutex<T> {
+    const fn new(value: T) -> GlobalMutex<T> {
+        GlobalMutex(Mutex::new(value))
+    }
+
+    /// Runs `f` on the value, waiting for any other thread that has it locked.
+    fn with<R: Default>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        #[cfg(feature = "panic-hook")]
+        let _in_use = StateInUse::enter();
+        f(&mut self.0.lock())
+    }
+
+    /// Runs `f` on the value, or returns `None` if another thread has it locked.
+    fn try_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        #[cfg(feature = "panic-hook")]
+        let _in_use = StateInUse::enter();
+        self.0.try_lock().map(|mut value| f(&mut value))
+    }
+}
+
+#[cfg(all(feature = "critical_section", not(feature = "no_global")))]
+impl<T> GlobalMutex<T> {
+    const fn new(value: T) -> GlobalMutex<T> {
+        GlobalMutex(critical_section::Mutex::new(core::cell::RefCell::new(value)))
+    }
+
+    /// Runs `f` on the value inside a critical section.
+    /// A re-entrant call, made from inside `f` or from a callback it runs, does not run its closure and returns
+    /// `R::default()` instead, where a `spin::Mutex` would deadlock.
+    fn with<R: Default>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.try_with(f).unwrap_or_default()
+    }
+
+    /// Runs `f` on the value inside a critical section, or returns `None` if the call is re-entrant.
+    fn try_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        #[cfg(feature = "panic-hook")]
+        let _in_use = StateInUse::enter();
+        critical_section::with(|cs| self.0.borrow(cs).try_borrow_mut().ok().map(|mut value| f(&mut value)))
+    }
+}
+
+#[cfg(all(feature = "panic-hook", not(feature = "no_global")))]
+std::thread_local! {
+    /// How many `GlobalMutex::with` calls are running on this thread. The panic hook only logs when this is 0,
+    /// because a panic raised inside one of them, e.g. by a listener, could otherwise deadlock on the state it holds.
+    static STATE_IN_USE: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// Counts this thread as using the global state until it is dropped, which also happens while a panic unwinds.
+#[cfg(all(feature = "panic-hook", not(feature = "no_global")))]
+struct StateInUse;
+
+#[cfg(all(feature = "panic-hook", not(feature = "no_global")))]
+impl StateInUse {
+    fn enter() -> StateInUse {
+        STATE_IN_USE.with(|depth| depth.set(depth.get() + 1));
+        StateInUse
+    }
+}
+
+#[cfg(all(feature = "panic-hook", not(feature = "no_global")))]
+impl Drop for StateInUse {
+    fn drop(&mut self) {
+        STATE_IN_USE.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+#[cfg(not(feature = "no_global"))]
+static NEXT_LISTENER_ID: AtomicU64 = AtomicU64::new(0);
+#[cfg(not(feature = "no_global"))]
+static GLOBAL_MIN_LEVEL: AtomicU8 = AtomicU8::new(0);
+#[cfg(not(feature = "no_global"))]
+static DEDUPLICATION: AtomicBool = AtomicBool::new(false);
+#[cfg(not(feature = "no_global"))]
+static SILENCE_ALL: AtomicBool = AtomicBool::new(false);
+#[cfg(not(feature = "no_global"))]
+static AUTO_SANITIZE: AtomicBool = AtomicBool::new(false);
+#[cfg(not(feature = "no_global"))]
+static CHANNEL_ALLOWLIST: GlobalMutex<Option<Vec<String>>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+#[cfg(not(feature = "no_global"))]
+static LOG_COUNT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(not(feature = "no_global"))]
+static CLOCK: GlobalMutex<Option<fn() -> u64>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static OOM_HANDLER: GlobalMutex<Option<fn()>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static ASSERT_HANDLER: GlobalMutex<Option<fn() -> !>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static MAX_MESSAGE_LENGTH: GlobalMutex<Option<usize>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static LOG_PREFIX: GlobalMutex<&'static str> = GlobalMutex::new("");
+#[cfg(not(feature = "no_global"))]
+static OVERFLOW_HANDLER: GlobalMutex<Option<fn(&Log)>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static OVERFLOW_COUNT: AtomicU64 = AtomicU64::new(0);
+#[cfg(not(feature = "no_global"))]
+static OUT_OF_MEMORY: AtomicBool = AtomicBool::new(false);
+#[cfg(not(feature = "no_global"))]
+static PRIMARY_LISTENER: GlobalMutex<Option<ListenerId>> = GlobalMutex::new(None);
+#[cfg(not(feature = "no_global"))]
+static PENDING_REMOVALS: GlobalMutex<Vec<Log>> = GlobalMutex::new(Vec::new());
+
+/// A registered listener. `listener` is taken out while one of its hooks runs with the list unlocked,
+/// and the events it misses in the meantime are kept in `missed` and passed to it once it is put back.
+#[cfg(not(feature = "no_global"))]
+struct ListenerEntry {
+    id: ListenerId,
+    priority: i32,
+    listener: Option<Box<dyn LogListener>>,
+    missed: Vec<MissedEvent>,
+}
+
+#[cfg(not(feature = "no_global"))]
+enum MissedEvent {
+    Log(Log),
+    Remove(Log),
+}
+
+#[cfg(not(feature = "no_global"))]
+impl ListenerEntry {
+    fn new(id: ListenerId, priority: i32, listener: Box<dyn LogListener>) -> ListenerEntry {
+        ListenerEntry {
+            id,
+            priority,
+            listener: Some(listener),
+            missed: Vec::new(),
+        }
+    }
+
+    fn on_log(&mut self, log: &Log) {
+        match &mut self.listener {
+            Some(listener) if listener.filter_hook(log) => match try_clone_log(log) {
+                Some(log) => listener.on_log(log),
+                None => note_out_of_memory(),
+            },
+            Some(_) => {}
+            None => match (self.missed.try_reserve(1), try_clone_log(log)) {
+                (Ok(()), Some(log)) => self.missed.push(MissedEvent::Log(log)),
+                _ => note_out_of_memory(),
+            },
+        }
+    }
+
+    fn on_remove(&mut self, log: &Log) {
+        match &mut self.listener {
+            Some(listener) => listener.on_remove(log),
+            None => match (self.missed.try_reserve(1), try_clone_log(log)) {
+                (Ok(()), Some(log)) => self.missed.push(MissedEvent::Remove(log)),
+                _ => note_out_of_memory(),
+            },
+        }
+    }
+}
+
+#[cfg(not(feature = "no_global"))]
+type ListenerList = Vec<ListenerEntry>;
+
+/// Runs `f` on the listener list, then delivers any removal notifications queued while it ran.
+#[cfg(not(feature = "no_global"))]
+fn with_listener_list<R: Default>(f: impl FnOnce(&mut ListenerList) -> R) -> R {
+    let result = LOG_LISTENERS.with(f);
+    notify_pending_removals();
+    result
+}
+
+/// Passes every queued removal to the listeners' `on_remove`.
+/// If the listener list is locked, either by another thread or because the removal was made from inside a listener
+/// callback, the notifications stay queued and are delivered by whoever holds the lock once it is released.
+#[cfg(not(feature = "no_global"))]
+fn notify_pending_removals() {
+    while PENDING_REMOVALS.with(|pending| !pending.is_empty()) {
+        let delivered = LOG_LISTENERS.try_with(|listeners| {
+            let pending = PENDING_REMOVALS.with(core::mem::take);
+            for removed in &pending {
+                for entry in listeners.iter_mut() {
+                    entry.on_remove(removed);
+                }
+            }
+        });
+        if delivered.is_none() {
+            return;
+        }
+    }
+}
+
+#[cfg(not(feature = "no_global"))]
+lazy_static! {
+    static ref LOGS: Arc<GlobalMutex<Box<dyn LogStore + Send>>> = Arc::new(GlobalMutex::new(Box::new(VecLogStore::new())));
+    static ref LOG_CAPACITY: Arc<GlobalMutex<Option<usize>>> = Arc::new(GlobalMutex::new(None));
+    static ref LOG_LISTENERS: Arc<GlobalMutex<ListenerList>> = Arc::new(GlobalMutex::new(Vec::new()));
+    static ref RATE_LIMITS: Arc<GlobalMutex<Vec<RateEntry>>> = Arc::new(GlobalMutex::new(Vec::new()));
+    static ref SILENCED_CHANNELS: Arc<GlobalMutex<Vec<String>>> = Arc::new(GlobalMutex::new(Vec::new()));
+    static ref LOGGED_ONCE: Arc<GlobalMutex<BTreeSet<(u32, &'static str)>>> = Arc::new(GlobalMutex::new(BTreeSet::new()));
+}
+
+/// The rate-limiting and sampling state of a single channel.
+#[cfg(not(feature = "no_global"))]
+struct RateEntry {
+    channel: String,
+    max_per_window: u32,
+    window_ticks: u64,
+    window_start: Option<u64>,
+    calls: u64,
+    count: u32,
+    suppressed: u32,
+    sample_rate: u32,
+    sampled: u32,
+}
+
+#[cfg(not(feature = "no_global"))]
+impl RateEntry {
+    fn new(channel: &str) -> RateEntry {
+        RateEntry {
+            channel: String::from(channel),
+            max_per_window: u32::MAX,
+            window_ticks: u64::MAX,
+            window_start: None,
+            calls: 0,
+            count: 0,
+            suppressed: 0,
+            sample_rate: 1,
+            sampled: 0,
+        }
+    }
+
+    /// Finds the entry for the channel, creating one with no limits if there is none.
+    fn find_or_insert<'a>(rate_limits: &'a mut Vec<RateEntry>, channel: &str) -> &'a mut RateEntry {
+        match rate_limits.iter().position(|entry| entry.channel == channel) {
+            Some(index) => &mut rate_limits[index],
+            None => {
+                rate_limits.push(RateEntry::new(channel));
+                rate_limits.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Records a log arriving on the channel, returning whether it is kept by sampling.
+    fn sample(&mut self) -> bool {
+        if self.sample_rate == 0 {
+            return false;
+        }
+        let keep = self.sampled == 0;
+        self.sampled = (self.sampled + 1) % self.sample_rate;
+        keep
+    }
+
+    /// Records a log arriving at `now`, returning whether it may be stored and how many logs
+    /// were suppressed in the window that just ended, if any.
+    fn check(&mut self, now: u64) -> (bool, Option<u32>) {
+        let mut report = None;
+        let window_over = match self.window_start {
+            Some(start) => now.saturating_sub(start) >= self.window_ticks,
+            None => true,
+        };
+        if window_over {
+            if self.suppressed > 0 {
+                report = Some(self.suppressed);
+            }
+            self.window_start = Some(now);
+            self.count = 0;
+            self.suppressed = 0;
+        }
+        if self.count < self.max_per_window {
+            self.count += 1;
+            (true, report)
+        } else {
+            self.suppressed = self.suppressed.saturating_add(1);
+            (false, report)
+        }
+    }
+}
+
+/// Initializes the logging system without a listener.
+/// This is equivalent to `clear_logs()` followed by removing all listeners, and also makes the log store unbounded
+/// and resets the count of logs dropped because the store was full.
+/// Note that the `init!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::init;
+/// init();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn init() {
+    init_inner(None, None);
+}
+
+/// Resets the logging system, bounding the log store to `capacity` logs if given and making `listener`, if given,
+/// the only listener. The `init*` functions that start from an empty store are wrappers around this.
+#[cfg(not(feature = "no_global"))]
+fn init_inner(capacity: Option<usize>, listener: Option<Box<dyn LogListener>>) {
+    clear_logs();
+    OVERFLOW_COUNT.store(0, Ordering::Relaxed);
+    LOGS.with(|logs| {
+        if let Some(capacity) = capacity {
+            logs.reserve(capacity);
+        }
+        LOG_CAPACITY.with(|stored| *stored = capacity);
+    });
+    report_out_of_memory();
+    replace_listeners(listener);
+}
+
+/// Removes every listener, registers `listener` as the primary one if given, and calls `on_init` on it.
+#[cfg(not(feature = "no_global"))]
+fn replace_listeners(listener: Option<Box<dyn LogListener>>) {
+    with_listener_list(|listeners| {
+        listeners.clear();
+        let primary = listener.map(|listener| {
+            let id = ListenerId::next();
+            listeners.push(ListenerEntry::new(id, 0, listener));
+            id
+        });
+        PRIMARY_LISTENER.with(|stored| *stored = primary);
+    });
+    with_listeners_released(|listener| listener.on_init());
+}
+
+/// Removes all logs from the store, leaving listeners and the log capacity untouched.
+/// Useful for rotating the log store at a checkpoint.
+/// Note that the `clear_logs!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{clear_logs, log, traceback};
+/// log!("Test log message");
+/// clear_logs();
+/// assert!(traceback!().is_empty());
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_logs() {
+    LOGS.with(|logs| {
+        logs.clear();
+        LOG_COUNT.store(0, Ordering::Relaxed);
+    });
+    with_listeners_released(|listener| listener.on_flush());
+}
+
+/// Calls `f` on every registered listener with neither the log store nor the listener list locked.
+/// Each listener is taken out of the list only while `f` runs on it, and the logs and removals it misses
+/// in the meantime are passed to it afterwards. Listeners registered while `f` runs are not called,
+/// and a listener unregistered while `f` runs on it is dropped once `f` returns.
+#[cfg(not(feature = "no_global"))]
+fn with_listeners_released(mut f: impl FnMut(&mut dyn LogListener)) {
+    let ids: Vec<ListenerId> = with_listener_list(|listeners| listeners.iter().map(|entry| entry.id).collect());
+    for id in ids {
+        let listener = with_listener_list(|listeners| {
+            listeners.iter_mut().find(|entry| entry.id == id).and_then(|entry| entry.listener.take())
+        });
+        let mut listener = match listener {
+            Some(listener) => listener,
+            None => continue,
+        };
+        f(listener.as_mut());
+        let mut listener = Some(listener);
+        loop {
+            let missed = with_listener_list(|listeners| {
+                let entry = listeners.iter_mut().find(|entry| entry.id == id)?;
+                if entry.missed.is_empty() {
+                    // A listener swapped in while this one was taken out replaces it.
+                    if entry.listener.is_none() {
+                        entry.listener = listener.take();
+                    }
+                    return None;
+                }
+                Some(core::mem::take(&mut entry.missed))
+            });
+            let (Some(missed), Some(listener)) = (missed, listener.as_mut()) else {
+                break;
+            };
+            for event in missed {
+                match event {
+                    MissedEvent::Log(log) if listener.filter_hook(&log) => listener.on_log(log),
+                    MissedEvent::Log(_) => {}
+                    MissedEvent::Remove(log) => listener.on_remove(&log),
+                }
+            }
+        }
+    }
+}
+
+/// Initializes the logging system without a listener, storing at most `capacity` logs.
+/// Once the store is full, the oldest log is discarded to make room for each new one.
+/// Note that the `init!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init_with_capacity, log_capacity};
+/// init_with_capacity(64);
+/// assert_eq!(log_capacity(), Some(64));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn init_with_capacity(capacity: usize) {
+    init_inner(Some(capacity), None);
+}
+
+/// Initializes the logging system with a listener.
+/// Note that the `init!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init_with_listener, LogListener};
+/// struct MyLogListener;
+/// 
+/// impl LogListener for MyLogListener {
+///    fn on_log(&mut self, log: breadcrumbs::Log) {
+///       println!("{}", log);
+///   }
+/// }
+/// 
+/// init_with_listener(Box::new(MyLogListener));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn init_with_listener(listener: Box<dyn LogListener>) {
+    init_inner(None, Some(listener));
+}
+
+/// Initializes the logging system with a bounded log store of `capacity` logs and a listener,
+/// the usual configuration for memory-constrained devices.
+/// Note that the `init!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init_with_capacity_and_listener, log_capacity, Log};
+/// init_with_capacity_and_listener(64, Box::new(|log: Log| println!("{}", log)));
+/// assert_eq!(log_capacity(), Some(64));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn init_with_capacity_and_listener(capacity: usize, listener: Box<dyn LogListener>) {
+    init_inner(Some(capacity), Some(listener));
+}
+
+/// Initializes the logging system with a listener, keeping the logs already in the store.
+/// Any previously registered listeners are replaced. Useful in firmware that logs during early boot,
+/// before the application's own initialization runs.
+/// ```rust
+/// use breadcrumbs::{init_append, log, traceback};
+/// log!("Early boot log");
+/// init_append(Box::new(|log| println!("{}", log)));
+/// assert_eq!(traceback!().len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn init_append(listener: Box<dyn LogListener>) {
+    replace_listeners(Some(listener));
+}
+
+/// Initializes the logging system without changing anything, keeping the logs already in the store and any listeners.
+/// This exists for symmetry with `init_append`, so that lazily-initialized code can always call an initializer.
+/// ```rust
+/// use breadcrumbs::{init_append_no_listener, log, traceback};
+/// log!("Early boot log");
+/// init_append_no_listener();
+/// assert_eq!(traceback!().len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn init_append_no_listener() {}
+
+/// Initializes the logging system without a listener, configured from the environment.
+/// `BREADCRUMBS_LEVEL` sets the global minimum level by name, e.g. `Warn`, and defaults to `Verbose` when unset.
+/// `BREADCRUMBS_CHANNELS`, if set, is a comma-separated allowlist of the only channels to keep; see `set_channel_allowlist`.
+/// Nothing is changed if a variable cannot be read or parsed.
+/// This requires the `std` feature.
+/// ```rust
+/// # #[cfg(feature = "std")]
+/// # {
+/// use breadcrumbs::{init_from_env, get_global_min_level, LogLevel};
+/// std::env::set_var("BREADCRUMBS_LEVEL", "Warn");
+/// init_from_env().unwrap();
+/// assert_eq!(get_global_min_level(), LogLevel::Warn);
+/// # }
+/// ```
+#[cfg(all(feature = "std", not(feature = "no_global")))]
+pub fn init_from_env() -> Result<(), EnvInitError> {
+    let level = match std::env::var("BREADCRUMBS_LEVEL") {
+        Ok(level) => LogLevel::try_from_str(level.trim()).map_err(EnvInitError::InvalidLevel)?,
+        Err(std::env::VarError::NotPresent) => LogLevel::all(),
+        Err(std::env::VarError::NotUnicode(_)) => return Err(EnvInitError::NotUnicode("BREADCRUMBS_LEVEL")),
+    };
+    let channels = match std::env::var("BREADCRUMBS_CHANNELS") {
+        Ok(channels) => Some(channels),
+        Err(std::env::VarError::NotPresent) => None,
+        Err(std::env::VarError::NotUnicode(_)) => return Err(EnvInitError::NotUnicode("BREADCRUMBS_CHANNELS")),
+    };
+    init();
+    set_global_min_level(level);
+    match channels {
+        Some(channels) => set_channel_allowlist(&channels.split(',').map(str::trim).collect::<Vec<&str>>()),
+        None => clear_channel_allowlist(),
+    }
+    Ok(())
+}
+
+/// The error returned by `init_from_env` when an environment variable is set but cannot be used.
+/// An unset variable is not an error and leaves the corresponding setting at its default.
+/// This requires the `std` feature.
+#[cfg(all(feature = "std", not(feature = "no_global")))]
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EnvInitError {
+    /// `BREADCRUMBS_LEVEL` is not the name of a log level.
+    InvalidLevel(UnknownLogLevel),
+    /// The named variable is not valid Unicode.
+    NotUnicode(&'static str),
+}
+
+#[cfg(all(feature = "std", not(feature = "no_global")))]
+impl core::fmt::Display for EnvInitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            EnvInitError::InvalidLevel(error) => write!(f, "BREADCRUMBS_LEVEL: {}", error),
+            EnvInitError::NotUnicode(variable) => write!(f, "{} is not valid unicode", variable),
+        }
+    }
+}
+
+/// Registers an additional listener without clearing the log store or any existing listeners.
+/// All registered listeners receive every log, in order of priority and then in the order they were added.
+/// Note that the `add_listener!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{add_listener, remove_listener, LogListener};
+/// struct MyLogListener;
+///
+/// impl LogListener for MyLogListener {
+///    fn on_log(&mut self, log: breadcrumbs::Log) {
+///       println!("{}", log);
+///   }
+/// }
+///
+/// let id = add_listener(Box::new(MyLogListener));
+/// remove_listener(id);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn add_listener(listener: Box<dyn LogListener>) -> ListenerId {
+    add_listener_with_priority(listener, 0)
+}
+
+/// Registers an additional listener that is notified before every listener with a lower priority.
+/// Listeners with the same priority are notified in the order they were added, and `add_listener` uses a priority of 0.
+/// Note that the `add_listener!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{add_listener_with_priority, remove_listener, Log};
+/// let id = add_listener_with_priority(Box::new(|log: Log| println!("ALERT: {}", log)), 10);
+/// remove_listener(id);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn add_listener_with_priority(listener: Box<dyn LogListener>, priority: i32) -> ListenerId {
+    let id = ListenerId::next();
+    with_listener_list(|listeners| {
+        let index = listeners.iter().position(|entry| entry.priority < priority).unwrap_or(listeners.len());
+        listeners.insert(index, ListenerEntry::new(id, priority, listener));
+    });
+    id
+}
+
+/// Unregisters the listener with the given `ListenerId`.
+/// Does nothing if no such listener is registered.
+/// Note that the `remove_listener!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{add_listener, remove_listener, LogListener};
+/// struct MyLogListener;
+///
+/// impl LogListener for MyLogListener {
+///    fn on_log(&mut self, log: breadcrumbs::Log) {
+///       println!("{}", log);
+///   }
+/// }
+///
+/// let id = add_listener(Box::new(MyLogListener));
+/// remove_listener(id);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn remove_listener(id: ListenerId) {
+    with_listener_list(|listeners| listeners.retain(|entry| entry.id != id));
+}
+
+/// Replaces the listener registered by `init_with_listener()`, `init_with_capacity_and_listener()` or `init_append()`
+/// with `listener`, returning the old one so that the caller can flush it.
+/// Unlike calling `init_with_listener()` again, the stored logs and any listeners added with `add_listener()` are kept.
+/// If there is no such listener, `listener` is registered in its place and `None` is returned.
+/// The listener list is only locked while the two listeners are exchanged, and `on_init` is not called.
+/// If the old listener is running `on_flush` or `on_init` on another thread, `None` is returned and it is dropped once that hook returns.
+/// Note that the `swap_listener!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init_with_listener, log, swap_listener, traceback, Log};
+/// init_with_listener(Box::new(|log: Log| println!("{}", log)));
+/// log!("Booted");
+/// let old = swap_listener(Box::new(|log: Log| println!("uart: {}", log)));
+/// assert!(old.is_some());
+/// assert_eq!(traceback!().len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn swap_listener(listener: Box<dyn LogListener>) -> Option<Box<dyn LogListener>> {
+    with_listener_list(|listeners| PRIMARY_LISTENER.with(|primary| {
+        if let Some(entry) = listeners.iter_mut().find(|entry| Some(entry.id) == *primary) {
+            return entry.listener.replace(listener);
+        }
+        let id = ListenerId::next();
+        let index = listeners.iter().position(|entry| entry.priority < 0).unwrap_or(listeners.len());
+        listeners.insert(index, ListenerEntry::new(id, 0, listener));
+        *primary = Some(id);
+        None
+    }))
+}
+
+/// Returns the maximum number of logs kept in the store, or `None` if it is unbounded.
+/// ```rust
+/// use breadcrumbs::{init, log_capacity};
+/// init();
+/// assert_eq!(log_capacity(), None);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn log_capacity() -> Option<usize> {
+    LOG_CAPACITY.with(|capacity| *capacity)
+}
+
+/// Changes the maximum number of logs kept in the store at runtime.
+/// If the store currently holds more than `capacity` logs, the oldest ones are discarded.
+/// ```rust
+/// use breadcrumbs::{set_log_capacity, log_capacity};
+/// set_log_capacity(16);
+/// assert_eq!(log_capacity(), Some(16));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_log_capacity(capacity: usize) {
+    LOGS.with(|logs| {
+        while logs.len() > capacity {
+            logs.remove_at(0);
+        }
+        LOG_COUNT.store(logs.len(), Ordering::Relaxed);
+        LOG_CAPACITY.with(|stored| *stored = Some(capacity));
+    });
+}
+
+/// Replaces the log store, discarding every log held by the previous one.
+/// The store is kept across calls to `init()`, which only clears it.
+/// ```rust
+/// use breadcrumbs::{set_log_store, log, traceback, VecLogStore};
+/// set_log_store(Box::new(VecLogStore::with_capacity(128)));
+/// log!("Test log message");
+/// assert_eq!(traceback!().len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_log_store(store: Box<dyn LogStore + Send>) {
+    LOGS.with(|logs| {
+        *logs = store;
+        LOG_COUNT.store(logs.len(), Ordering::Relaxed);
+    });
+}
+
+/// Returns the number of stored logs without locking the log store.
+/// The count may be momentarily stale while another thread is logging, but it is always safe to read,
+/// e.g. to decide whether to wake a task that flushes the store.
+/// ```rust
+/// use breadcrumbs::{init, log, log_len};
+/// init!();
+/// log!("Test log message");
+/// assert_eq!(log_len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn log_len() -> usize {
+    LOG_COUNT.load(Ordering::Relaxed)
+}
+
+
+/// Sets the global minimum log level.
+/// Logs below this level are discarded before they are stored or passed to any listener,
+/// and the logging macros skip evaluating their message entirely.
+/// The global minimum log level is not reset by `init()`.
+/// ```rust
+/// use breadcrumbs::{set_global_min_level, get_global_min_level, LogLevel};
+/// set_global_min_level(LogLevel::Warn);
+/// assert_eq!(get_global_min_level(), LogLevel::Warn);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn set_global_min_level(level: LogLevel) {
+    GLOBAL_MIN_LEVEL.store(level.numeric(), Ordering::Relaxed);
+}
+
+/// Returns the global minimum log level, which defaults to `LogLevel::Verbose`.
+/// ```rust
+/// use breadcrumbs::{get_global_min_level, LogLevel};
+/// assert_eq!(get_global_min_level(), LogLevel::Verbose);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn get_global_min_level() -> LogLevel {
+    LogLevel::from_numeric(GLOBAL_MIN_LEVEL.load(Ordering::Relaxed)).unwrap_or_default()
+}
+
+/// Checks if a log of the given level passes the global minimum log level.
+/// This is used by the logging macros and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _level_enabled(level: LogLevel) -> bool {
+    level.numeric() >= GLOBAL_MIN_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Enables deduplication of consecutive identical logs.
+/// While enabled, a log with the same channel, level and message as the most recent stored log
+/// increments that log's `occurrences` instead of being stored again.
+/// Listeners are still notified of every log, receiving the deduplicated log with its updated count.
+/// ```rust
+/// use breadcrumbs::{init, log, traceback, enable_deduplication, LogLevel};
+/// init!();
+/// enable_deduplication();
+/// log!(LogLevel::Warn, "adc", "ADC overrange");
+/// log!(LogLevel::Warn, "adc", "ADC overrange");
+/// assert_eq!(traceback!().0.len(), 1);
+/// assert_eq!(traceback!().0[0].occurrences, 2);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn enable_deduplication() {
+    DEDUPLICATION.store(true, Ordering::Relaxed);
+}
+
+/// Enables or disables calling `Log::sanitize()` on every new log before it is stored, which is disabled by default.
+/// ```rust
+/// use breadcrumbs::{log, last_log, set_auto_sanitize};
+/// set_auto_sanitize(true);
+/// log!("bell\x07");
+/// assert_eq!(last_log!().unwrap().message, "bell?");
+/// set_auto_sanitize(false);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn set_auto_sanitize(enabled: bool) {
+    AUTO_SANITIZE.store(enabled, Ordering::Relaxed);
+}
+
+/// Disables deduplication of consecutive identical logs, which is the default.
+/// ```rust
+/// use breadcrumbs::disable_deduplication;
+/// disable_deduplication();
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn disable_deduplication() {
+    DEDUPLICATION.store(false, Ordering::Relaxed);
+}
+
+/// Registers a clock used to timestamp every new log.
+/// The clock can be any function returning a tick value, such as an RTC reading or a cycle counter.
+/// It is called without any lock held, so it may call `set_clock` itself, e.g. to switch to another timer once that is running.
+/// ```rust
+/// use breadcrumbs::{init, log, traceback, set_clock};
+/// fn ticks() -> u64 {
+///     1234567
+/// }
+///
+/// init!();
+/// set_clock(ticks);
+/// log!("Test log message");
+/// assert_eq!(traceback!().0[0].timestamp, Some(1234567));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_clock(clock: fn() -> u64) {
+    CLOCK.with(|stored| *stored = Some(clock));
+}
+
+/// Unregisters the clock, so new logs are no longer timestamped.
+/// ```rust
+/// use breadcrumbs::clear_clock;
+/// clear_clock();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_clock() {
+    CLOCK.with(|clock| *clock = None);
+}
+
+/// Registers a function to be called when storing a log or passing it to the listeners fails to allocate memory.
+/// The log is dropped instead of aborting, counted by `get_overflow_count`, and the handler can be used to record or react to the failure.
+/// ```rust
+/// use breadcrumbs::{set_oom_handler, clear_oom_handler};
+/// fn on_oom() {
+///     // e.g. toggle a status LED
+/// }
+///
+/// set_oom_handler(on_oom);
+/// clear_oom_handler();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_oom_handler(handler: fn()) {
+    OOM_HANDLER.with(|stored| *stored = Some(handler));
+}
+
+/// Unregisters the allocation failure handler, so failed allocations drop the log silently.
+/// ```rust
+/// use breadcrumbs::clear_oom_handler;
+/// clear_oom_handler();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_oom_handler() {
+    OOM_HANDLER.with(|handler| *handler = None);
+}
+
+/// Registers a function to be called whenever a log is dropped because the bounded log store is full.
+/// The handler receives the dropped log, which is the oldest one when a log is evicted, and is called with nothing locked.
+/// ```rust
+/// use breadcrumbs::{set_overflow_handler, clear_overflow_handler, Log};
+/// fn on_overflow(dropped: &Log) {
+///     // e.g. forward the log over the UART before it is lost
+/// }
+///
+/// set_overflow_handler(on_overflow);
+/// clear_overflow_handler();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_overflow_handler(handler: fn(&Log)) {
+    OVERFLOW_HANDLER.with(|stored| *stored = Some(handler));
+}
+
+/// Unregisters the overflow handler.
+/// ```rust
+/// use breadcrumbs::clear_overflow_handler;
+/// clear_overflow_handler();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_overflow_handler() {
+    OVERFLOW_HANDLER.with(|handler| *handler = None);
+}
+
+/// Returns how many logs have been dropped because the bounded log store was full, or because there was no memory left to store them,
+/// since the logging system was last initialized.
+/// ```rust
+/// use breadcrumbs::{init, log, get_overflow_count};
+/// init!(capacity = 1);
+/// log!("First");
+/// log!("Second");
+/// assert_eq!(get_overflow_count(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn get_overflow_count() -> u64 {
+    OVERFLOW_COUNT.load(Ordering::Relaxed)
+}
+
+/// Limits the length of stored messages to `n` bytes. Longer messages are cut at the last character boundary
+/// within `n` bytes and `"…"` is appended, so a single huge message cannot exhaust the heap.
+/// ```rust
+/// use breadcrumbs::{set_max_message_length, clear_max_message_length, log, last_log};
+/// set_max_message_length(4);
+/// log!("Test log message");
+/// assert_eq!(last_log!().unwrap().message, "Test…");
+/// clear_max_message_length();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_max_message_length(n: usize) {
+    MAX_MESSAGE_LENGTH.with(|max| *max = Some(n));
+}
+
+/// Removes the limit on the length of stored messages.
+/// ```rust
+/// use breadcrumbs::{clear_max_message_length, get_max_message_length};
+/// clear_max_message_length();
+/// assert_eq!(get_max_message_length(), None);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_max_message_length() {
+    MAX_MESSAGE_LENGTH.with(|max| *max = None);
+}
+
+/// Returns the limit on the length of stored messages in bytes, or `None` if messages are not truncated.
+/// ```rust
+/// use breadcrumbs::{set_max_message_length, clear_max_message_length, get_max_message_length};
+/// set_max_message_length(80);
+/// assert_eq!(get_max_message_length(), Some(80));
+/// clear_max_message_length();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_max_message_length() -> Option<usize> {
+    MAX_MESSAGE_LENGTH.with(|max| *max)
+}
+
+/// Sets a prefix that is prepended to the message of every log before it is stored,
+/// e.g. to tell apart the logs of several firmware images sharing one UART.
+/// ```rust
+/// use breadcrumbs::{set_log_prefix, clear_log_prefix, log, last_log};
+/// set_log_prefix("[IMG0] ");
+/// log!("Booted");
+/// assert_eq!(last_log!().unwrap().message, "[IMG0] Booted");
+/// clear_log_prefix();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_log_prefix(prefix: &'static str) {
+    LOG_PREFIX.with(|stored| *stored = prefix);
+}
+
+/// Removes the prefix prepended to log messages.
+/// ```rust
+/// use breadcrumbs::{clear_log_prefix, get_log_prefix};
+/// clear_log_prefix();
+/// assert_eq!(get_log_prefix(), "");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_log_prefix() {
+    LOG_PREFIX.with(|prefix| *prefix = "");
+}
+
+/// Returns the prefix prepended to log messages, which is empty by default.
+/// ```rust
+/// use breadcrumbs::{set_log_prefix, clear_log_prefix, get_log_prefix};
+/// set_log_prefix("[IMG1] ");
+/// assert_eq!(get_log_prefix(), "[IMG1] ");
+/// clear_log_prefix();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_log_prefix() -> &'static str {
+    LOG_PREFIX.with(|prefix| *prefix)
+}
+
+/// Truncates the message to at most `max` bytes on a character boundary, appending `"…"` if anything was cut.
+#[cfg(any(feature = "heapless", not(feature = "no_global")))]
+fn truncate_message(message: &mut String, max: usize) {
+    if message.len() <= max {
+        return;
+    }
+    let mut end = max;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    message.truncate(end);
+    message.push('…');
+}
+
+/// Limits the channel to at most `max_per_window` logs every `window_ticks` ticks; further logs are dropped.
+/// Ticks are measured with the clock registered by `set_clock`, or by counting the logs on the channel if no clock is registered.
+/// When a window in which logs were dropped is over, the next log on the channel is preceded by a synthetic
+/// `"[channel] rate limit: N messages suppressed"` log at `LogLevel::Warn`.
+/// ```rust
+/// use breadcrumbs::{init, log, traceback, set_rate_limit, clear_rate_limits, LogLevel};
+/// init!();
+/// set_rate_limit("irq", 2, 100);
+/// for _ in 0..10 {
+///     log!(LogLevel::Info, "irq", "IRQ fired");
+/// }
+/// assert_eq!(traceback!().len(), 2);
+/// clear_rate_limits();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_rate_limit(channel: &str, max_per_window: u32, window_ticks: u64) {
+    RATE_LIMITS.with(|rate_limits| {
+        let entry = RateEntry::find_or_insert(rate_limits, channel);
+        entry.max_per_window = max_per_window;
+        entry.window_ticks = window_ticks;
+    });
+}
+
+/// Removes the rate limits from all channels. Sample rates set by `set_channel_sample_rate` are kept.
+/// ```rust
+/// use breadcrumbs::clear_rate_limits;
+/// clear_rate_limits();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_rate_limits() {
+    RATE_LIMITS.with(|rate_limits| {
+        rate_limits.retain(|entry| entry.sample_rate != 1);
+        for entry in rate_limits.iter_mut() {
+            *entry = RateEntry { sample_rate: entry.sample_rate, sampled: entry.sampled, ..RateEntry::new(&entry.channel) };
+        }
+    });
+}
+
+/// Keeps only every `n`th log on the channel, starting with the first; the others are neither stored nor passed to listeners.
+/// A rate of 1, the default, keeps every log, and a rate of 0 silences the channel entirely.
+/// ```rust
+/// use breadcrumbs::{init, log, traceback, set_channel_sample_rate, LogLevel};
+/// init!();
+/// set_channel_sample_rate("telemetry", 10);
+/// for i in 0..100 {
+///     log!(LogLevel::Verbose, "telemetry", "Sample {}", i);
+/// }
+/// assert_eq!(traceback!().len(), 10);
+/// set_channel_sample_rate("telemetry", 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_channel_sample_rate(channel: &str, n: u32) {
+    RATE_LIMITS.with(|rate_limits| {
+        let entry = RateEntry::find_or_insert(rate_limits, channel);
+        entry.sample_rate = n;
+        entry.sampled = 0;
+    });
+}
+
+/// Mutes the channel, so its logs are discarded before they are stored or passed to any listener.
+/// ```rust
+/// use breadcrumbs::{init, log, traceback, silence_channel, unsilence_channel, LogLevel};
+/// init!();
+/// silence_channel("noisy");
+/// log!(LogLevel::Info, "noisy", "Test log message");
+/// assert!(traceback!().is_empty());
+/// unsilence_channel("noisy");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn silence_channel(channel: &str) {
+    SILENCED_CHANNELS.with(|silenced| {
+        if !silenced.iter().any(|silenced| silenced == channel) {
+            silenced.push(String::from(channel));
+        }
+    });
+}
+
+/// Unmutes a channel muted by `silence_channel`. This does not undo `silence_all`.
+/// ```rust
+/// use breadcrumbs::{silence_channel, unsilence_channel, is_channel_silenced};
+/// silence_channel("noisy");
+/// unsilence_channel("noisy");
+/// assert!(!is_channel_silenced("noisy"));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn unsilence_channel(channel: &str) {
+    SILENCED_CHANNELS.with(|silenced| silenced.retain(|silenced| silenced != channel));
+}
+
+/// Discards the logs of every channel that is not in `channels`, until `clear_channel_allowlist` or `unsilence_all` is called.
+/// This replaces any previous allowlist.
+/// ```rust
+/// use breadcrumbs::{set_channel_allowlist, clear_channel_allowlist, is_channel_silenced};
+/// set_channel_allowlist(&["motor", "net"]);
+/// assert!(!is_channel_silenced("net"));
+/// assert!(is_channel_silenced("telemetry"));
+/// clear_channel_allowlist();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_channel_allowlist(channels: &[&str]) {
+    let allowed = channels.iter().map(|channel| String::from(*channel)).collect();
+    CHANNEL_ALLOWLIST.with(|allowlist| *allowlist = Some(allowed));
+}
+
+/// Removes the channel allowlist, so logs are no longer discarded for being outside it.
+/// ```rust
+/// use breadcrumbs::{clear_channel_allowlist, is_channel_silenced};
+/// clear_channel_allowlist();
+/// assert!(!is_channel_silenced("telemetry"));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_channel_allowlist() {
+    CHANNEL_ALLOWLIST.with(|allowlist| *allowlist = None);
+}
+
+/// Checks if logs on the channel are currently discarded by `silence_channel`, `silence_all` or the channel allowlist.
+/// ```rust
+/// use breadcrumbs::{silence_channel, unsilence_channel, is_channel_silenced};
+/// silence_channel("noisy");
+/// assert!(is_channel_silenced("noisy"));
+/// assert!(!is_channel_silenced("quiet"));
+/// unsilence_channel("noisy");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn is_channel_silenced(channel: &str) -> bool {
+    if SILENCE_ALL.load(Ordering::Relaxed) || SILENCED_CHANNELS.with(|silenced| silenced.iter().any(|silenced| silenced == channel)) {
+        return true;
+    }
+    CHANNEL_ALLOWLIST.with(|allowlist| match allowlist {
+        Some(allowed) => !allowed.iter().any(|allowed| allowed == channel),
+        None => false,
+    })
+}
+
+/// Mutes every channel, including the default one, until `unsilence_all` is called.
+/// ```rust
+/// use breadcrumbs::{silence_all, unsilence_all, is_channel_silenced};
+/// silence_all();
+/// assert!(is_channel_silenced("any"));
+/// unsilence_all();
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+pub fn silence_all() {
+    SILENCE_ALL.store(true, Ordering::Relaxed);
+}
+
+/// Unmutes every channel, undoing `silence_all`, every `silence_channel` and the channel allowlist.
+/// ```rust
+/// use breadcrumbs::{silence_channel, unsilence_all, is_channel_silenced};
+/// silence_channel("noisy");
+/// unsilence_all();
+/// assert!(!is_channel_silenced("noisy"));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn unsilence_all() {
+    SILENCE_ALL.store(false, Ordering::Relaxed);
+    SILENCED_CHANNELS.with(|silenced| silenced.clear());
+    clear_channel_allowlist();
+}
+
+/// Returns the sample rate of the channel, which is 1 unless changed by `set_channel_sample_rate`.
+/// ```rust
+/// use breadcrumbs::get_channel_sample_rate;
+/// assert_eq!(get_channel_sample_rate("telemetry"), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_channel_sample_rate(channel: &str) -> u32 {
+    RATE_LIMITS.with(|rate_limits| rate_limits.iter().find(|entry| entry.channel == channel).map(|entry| entry.sample_rate))
+        .unwrap_or(1)
+}
+
+/// A macro for initializing the logging system.
+/// 
+/// # Use
+/// 
+/// To initialize the logging system without a listener, do not pass any arguments.
+/// 
+/// To initialize the logging system with a listener, pass a listener implementing `LogListener` as the first argument.
+/// 
+/// To initialize the logging system with a bounded log store, pass `capacity = n`, optionally followed by a listener.
+/// 
+/// # Examples
+/// 
+/// Initialize the logging system without a listener:
+/// ```
+/// use breadcrumbs::init;
+/// init!();
+/// ```
+/// 
+/// Initialize the logging system with a listener:
+/// ```
+/// use breadcrumbs::{init, LogListener};
+/// struct MyLogListener;
+/// 
+/// impl LogListener for MyLogListener {
+///   fn on_log(&mut self, log: breadcrumbs::Log) {
+///      println!("{}", log);
+///   }
+/// }
+/// 
+/// init!(MyLogListener);
+/// ```
+///
+/// Initialize the logging system with a bounded log store:
+/// ```
+/// use breadcrumbs::init;
+/// init!(capacity = 64);
+/// ```
+///
+/// Initialize the logging system with a bounded log store and a listener:
+/// ```
+/// use breadcrumbs::{init, Log};
+/// init!(capacity = 64, |log: Log| println!("{}", log));
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! init {
+    () => {
+        $crate::init()
+    };
+    (capacity = $capacity:expr) => {
+        $crate::init_with_capacity($capacity)
+    };
+    (capacity = $capacity:expr, $arg1:expr) => {
+        extern crate alloc;
+        use alloc::boxed::Box;
+        $crate::init_with_capacity_and_listener($capacity, Box::new($arg1))
+    };
+    ($arg1:expr) => {
+        extern crate alloc;
+        use alloc::boxed::Box;
+        $crate::init_with_listener(Box::new($arg1))
+    };
+}
+
+/// A macro for removing all logs from the store without resetting any listeners.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use breadcrumbs::{clear_logs, log};
+/// log!("Test log message");
+/// clear_logs!();
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! clear_logs {
+    () => {
+        $crate::clear_logs()
+    };
+}
+
+/// A macro for combining two listeners into a boxed `TeeListener`, ready to be registered.
+///
+/// # Examples
+///
+/// ```
+/// use breadcrumbs::{add_listener, tee_listeners, Log};
+/// let tee = tee_listeners!(|log: Log| println!("UART: {}", log), |log: Log| println!("USB: {}", log));
+/// add_listener(tee);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! tee_listeners {
+    ($arg1:expr, $arg2:expr) => {{
+        extern crate alloc;
+        alloc::boxed::Box::new($crate::TeeListener::new($arg1, $arg2))
+    }};
+}
+
+/// A macro for registering an additional listener, returning its `ListenerId`.
+///
+/// # Examples
+///
+/// ```
+/// use breadcrumbs::{add_listener, remove_listener, LogListener};
+/// struct MyLogListener;
+///
+/// impl LogListener for MyLogListener {
+///   fn on_log(&mut self, log: breadcrumbs::Log) {
+///      println!("{}", log);
+///   }
+/// }
+///
+/// let id = add_listener!(MyLogListener);
+/// remove_listener!(id);
+/// ```
+/// 
+/// Register a listener that is notified before those with a lower priority
+/// 
+/// ```
+/// use breadcrumbs::{add_listener, remove_listener, Log};
+/// let id = add_listener!(|log: Log| println!("ALERT: {}", log), priority = 10);
+/// remove_listener!(id);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! add_listener {
+    ($arg1:expr, priority = $arg2:expr) => {{
+        extern crate alloc;
+        $crate::add_listener_with_priority(alloc::boxed::Box::new($arg1), $arg2)
+    }};
+    ($arg1:expr) => {{
+        extern crate alloc;
+        $crate::add_listener(alloc::boxed::Box::new($arg1))
+    }};
+}
+
+/// A macro for unregistering a listener given its `ListenerId`.
+///
+/// # Examples
+///
+/// ```
+/// use breadcrumbs::{add_listener, remove_listener, LogListener};
+/// struct MyLogListener;
+///
+/// impl LogListener for MyLogListener {
+///   fn on_log(&mut self, log: breadcrumbs::Log) {
+///      println!("{}", log);
+///   }
+/// }
+///
+/// let id = add_listener!(MyLogListener);
+/// remove_listener!(id);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! remove_listener {
+    ($arg1:expr) => {
+        $crate::remove_listener($arg1)
+    };
+}
+
+/// A macro for replacing the listener registered when initializing the logging system, without clearing the stored logs.
+/// Returns the old listener, if there was one.
+///
+/// # Examples
+///
+/// ```
+/// use breadcrumbs::{init, swap_listener, Log};
+/// init!(|log: Log| println!("{}", log));
+/// let old = swap_listener!(|log: Log| println!("uart: {}", log));
+/// assert!(old.is_some());
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! swap_listener {
+    ($arg1:expr) => {{
+        extern crate alloc;
+        $crate::swap_listener(alloc::boxed::Box::new($arg1))
+    }};
+}
+
+/// Logs a message with an optional log level and channel. 
+/// The channel stays an `Option<String>` so that existing calls passing an owned string or a bare `None` keep compiling;
+/// use `log_to_channel` or the `log!` macro to log to a typed `LogChannel`.
+/// Note that the `log!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{log, LogLevel};
+/// log(Some(LogLevel::Info), Some(String::from("test_channel")), String::from("Test log message"));
+/// log(None, None, String::from("Test log message without a channel"));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn log(level: Option<LogLevel>, channel: Option<String>, message: String) {
+    let channel = channel.unwrap_or_default();
+    if !_level_enabled(level.unwrap_or_default()) || is_channel_silenced(&channel) {
+        return;
+    }
+    store_log(Log::new(channel, level.unwrap_or(LogLevel::Info), message));
+}
+
+/// Logs a message with an optional log level to a typed channel, which is the typed counterpart of `log()`.
+/// Note that the `log!` macro, which accepts typed channels too, is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{log_to_channel, LogLevel};
+/// log_to_channel(Some(LogLevel::Info), "test_channel", String::from("Test log message"));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn log_to_channel(level: Option<LogLevel>, channel: impl LogChannel, message: String) {
+    let channel = channel.channel_name();
+    if !_level_enabled(level.unwrap_or_default()) || is_channel_silenced(channel) {
+        return;
+    }
+    store_log(Log::new(String::from(channel), level.unwrap_or(LogLevel::Info), message));
+}
+
+/// A backend for the `log` crate facade that stores every record as a breadcrumbs log, so that crates logging
+/// with `log::info!` and friends feed into the traceback. The record's target becomes the channel,
+/// and `log::Level::Trace` maps to `LogLevel::Verbose`. The global minimum level and silenced channels apply.
+/// Note that `init_as_global_logger()` is the preferred method to install it.
+/// This requires the `log-facade` feature.
+#[cfg(all(feature = "log-facade", not(feature = "no_global")))]
+pub struct BreadcrumbsLogger;
+
+#[cfg(all(feature = "log-facade", not(feature = "no_global")))]
+impl BreadcrumbsLogger {
+    fn level(level: ::log::Level) -> LogLevel {
+        match level {
+            ::log::Level::Error => LogLevel::Error,
+            ::log::Level::Warn => LogLevel::Warn,
+            ::log::Level::Info => LogLevel::Info,
+            ::log::Level::Debug => LogLevel::Debug,
+            ::log::Level::Trace => LogLevel::Verbose,
+        }
+    }
+}
+
+#[cfg(all(feature = "log-facade", not(feature = "no_global")))]
+impl ::log::Log for BreadcrumbsLogger {
+    fn enabled(&self, metadata: &::log::Metadata) -> bool {
+        _level_enabled(BreadcrumbsLogger::level(metadata.level())) && !is_channel_silenced(metadata.target())
+    }
+
+    fn log(&self, record: &::log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut log = Log::new(String::from(record.target()), BreadcrumbsLogger::level(record.level()), record.args().to_string());
+        log.file = record.file_static();
+        log.line = record.line();
+        store_log(log);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs `BreadcrumbsLogger` as the backend of the `log` crate facade and sets its maximum level.
+/// Returns an error if another backend has already been installed.
+/// This requires the `log-facade` feature.
+/// ```rust
+/// # #[cfg(feature = "log-facade")]
+/// # {
+/// use breadcrumbs::{init_as_global_logger, traceback_channel};
+/// init_as_global_logger(log::LevelFilter::Info).unwrap();
+/// log::warn!(target: "net", "Link down");
+/// assert_eq!(traceback_channel!("net").0[0].message, "Link down");
+/// # }
+/// ```
+#[cfg(all(feature = "log-facade", not(feature = "no_global")))]
+pub fn init_as_global_logger(max_level: ::log::LevelFilter) -> Result<(), ::log::SetLoggerError> {
+    ::log::set_logger(&BreadcrumbsLogger)?;
+    ::log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Installs a panic hook that logs the message and location of every panic to the `"panic"` channel at `LogLevel::Critical`,
+/// then calls the previously installed hook. After a panic is caught with `catch_unwind`, the traceback contains it for post-mortem analysis.
+/// A panic raised on a thread that is inside breadcrumbs itself, such as inside a listener, is not logged, to avoid a deadlock.
+/// Panics on other threads are logged as usual, waiting for the thread that is logging if there is one.
+/// This requires the `panic-hook` feature.
+/// ```rust
+/// # #[cfg(feature = "panic-hook")]
+/// # {
+/// use breadcrumbs::{install_panic_hook, traceback_channel};
+/// install_panic_hook();
+/// let _ = std::panic::catch_unwind(|| panic!("Sensor offline"));
+/// assert!(traceback_channel!("panic").0[0].message.starts_with("Sensor offline"));
+/// # }
+/// ```
+#[cfg(all(feature = "panic-hook", not(feature = "no_global")))]
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = match info.payload().downcast_ref::<&str>() {
+            Some(message) => String::from(*message),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => String::from("Box<dyn Any>"),
+            },
+        };
+        let message = match info.location() {
+            Some(location) => format!("{} at {}:{}:{}", payload, location.file(), location.line(), location.column()),
+            None => payload,
+        };
+        if STATE_IN_USE.with(|depth| depth.get()) == 0 {
+            log(Some(LogLevel::Critical), Some(String::from("panic")), message);
+        }
+        previous(info);
+    }));
+}
+
+/// Logs a message along with the source-code location it originated from.
+/// This is used by the logging macros and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _log_with_location(level: Option<LogLevel>, channel: Option<&str>, message: String, file: &'static str, line: u32, column: u32) {
+    let channel = channel.unwrap_or(NO_CHANNEL);
+    if !_level_enabled(level.unwrap_or_default()) || is_channel_silenced(channel) {
+        return;
+    }
+    store_log(Log::with_location(String::from(channel), level.unwrap_or(LogLevel::Info), message, file, line, column));
+}
+
+/// Logs a message built by `message` along with the source-code location it originated from,
+/// calling `message` only if the log level is enabled and the channel is not silenced.
+/// This is used by the `log_lazy!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _log_lazy<F: FnOnce() -> String>(level: LogLevel, channel: &str, message: F, file: &'static str, line: u32, column: u32) {
+    if !_level_enabled(level) || is_channel_silenced(channel) {
+        return;
+    }
+    store_log(Log::with_location(String::from(channel), level, message(), file, line, column));
+}
+
+/// Logs `"→ name"` at `LogLevel::Verbose` and returns the current tick of the clock registered by `set_clock`, if any.
+/// Nothing is formatted or timed if verbose logs are disabled or the channel is silenced, and `None` is returned.
+/// This is used by the `log_measure!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _measure_start(channel: &str, name: &str, file: &'static str, line: u32, column: u32) -> Option<u64> {
+    if !_level_enabled(LogLevel::Verbose) || is_channel_silenced(channel) {
+        return None;
+    }
+    _log_with_location(Some(LogLevel::Verbose), Some(channel), format!("→ {}", name), file, line, column);
+    read_clock()
+}
+
+/// Logs `"← name"` at `LogLevel::Verbose`, followed by the ticks elapsed since `start` if a clock is registered.
+/// This is used by the `log_measure!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _measure_end(channel: &str, name: &str, start: Option<u64>, file: &'static str, line: u32, column: u32) {
+    if !_level_enabled(LogLevel::Verbose) || is_channel_silenced(channel) {
+        return;
+    }
+    let message = match (start, read_clock()) {
+        (Some(start), Some(now)) => format!("← {} ({} ticks)", name, now.saturating_sub(start)),
+        _ => format!("← {}", name),
+    };
+    _log_with_location(Some(LogLevel::Verbose), Some(channel), message, file, line, column);
+}
+
+/// Logs a message in several channels along with the source-code location it originated from.
+/// The log is dropped if every one of its channels is silenced.
+/// This is used by the `log!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _log_multi_with_location(level: LogLevel, channels: &[&str], message: String, file: &'static str, line: u32, column: u32) {
+    if !_level_enabled(level) || channels.iter().all(|channel| is_channel_silenced(channel)) {
+        return;
+    }
+    store_log(Log {
+        file: Some(file),
+        line: Some(line),
+        column: Some(column),
+        ..Log::new_multi(channels.iter().map(|channel| String::from(*channel)).collect(), level, message)
+    });
+}
+
+/// Logs a message with metadata along with the source-code location it originated from.
+/// This is used by the `log_meta!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _log_with_meta(level: LogLevel, channel: &str, message: String, meta: &[(&str, &str)], file: &'static str, line: u32, column: u32) {
+    if !_level_enabled(level) || is_channel_silenced(channel) {
+        return;
+    }
+    store_log(Log {
+        file: Some(file),
+        line: Some(line),
+        column: Some(column),
+        ..Log::new_with_meta(String::from(channel), level, message, meta)
+    });
+}
+
+/// Returns `true` if a `log_once!` call site has already logged its message.
+/// This is used by the `log_once!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _logged_once_at(file: &'static str, line: u32) -> bool {
+    LOGGED_ONCE.with(|logged| logged.contains(&(line, file)))
+}
+
+/// Logs a message along with the source-code location it originated from, unless that call site has already logged.
+/// The call site is only recorded once the log gets past the level, silencing, sampling and rate limiting checks.
+/// This is used by the `log_once!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _log_once_with_location(level: LogLevel, channel: &str, message: String, file: &'static str, line: u32, column: u32) {
+    if !_level_enabled(level) || is_channel_silenced(channel) || _logged_once_at(file, line) {
+        return;
+    }
+    if store_log(Log::with_location(String::from(channel), level, message, file, line, column)) {
+        LOGGED_ONCE.with(|logged| {
+            logged.insert((line, file));
+        });
+    }
+}
+
+/// Logs a failed `log_assert!` at `LogLevel::Critical`, then calls the assertion handler or panics.
+/// This is used by the `log_assert!` macro and is not part of the public API.
+#[cfg(not(feature = "no_global"))]
+#[doc(hidden)]
+pub fn _assert_failed(channel: &str, message: String, file: &'static str, line: u32, column: u32) -> ! {
+    // Bypass silencing, sampling and rate limits so that the failure is always recorded.
+    let timestamp = read_clock();
+    commit_log(Log::with_location(String::from(channel), LogLevel::Critical, message.clone(), file, line, column), timestamp);
+    let handler = ASSERT_HANDLER.with(|handler| *handler);
+    match handler {
+        Some(handler) => handler(),
+        None => panic!("{}", message),
+    }
+}
+
+/// Registers a function to be called instead of panicking when a `log_assert!` fails, such as a routine that resets the device.
+/// The failure has already been logged and passed to the listeners when the handler is called.
+/// ```rust
+/// use breadcrumbs::{set_assert_handler, clear_assert_handler};
+/// fn reset() -> ! {
+///     loop {}
+/// }
+///
+/// set_assert_handler(reset);
+/// clear_assert_handler();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn set_assert_handler(handler: fn() -> !) {
+    ASSERT_HANDLER.with(|stored| *stored = Some(handler));
+}
+
+/// Unregisters the assertion handler, so failed `log_assert!`s panic again.
+/// ```rust
+/// use breadcrumbs::clear_assert_handler;
+/// clear_assert_handler();
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn clear_assert_handler() {
+    ASSERT_HANDLER.with(|handler| *handler = None);
+}
+
+/// Passes the log through sampling and rate limiting and commits it, returning `false` if it was dropped on the way.
+#[cfg(not(feature = "no_global"))]
+fn store_log(log: Log) -> bool {
+    let timestamp = read_clock();
+    let checked = RATE_LIMITS.with(|rate_limits| {
+        let entry = rate_limits.iter_mut().find(|entry| entry.channel == log.channel)?;
+        if !entry.sample() {
+            return Some((false, None));
+        }
+        entry.calls += 1;
+        Some(entry.check(timestamp.unwrap_or(entry.calls)))
+    });
+    let (allowed, report) = checked.unwrap_or((true, None));
+    if let Some(suppressed) = report {
+        let message = format!("[{}] rate limit: {} messages suppressed", log.channel, suppressed);
+        commit_log(Log::new(log.channel.clone(), LogLevel::Warn, message), timestamp);
+    }
+    if allowed {
+        commit_log(log, timestamp);
+    }
+    allowed
+}
+
+#[cfg(not(feature = "no_global"))]
+fn commit_log(mut log: Log, timestamp: Option<u64>) {
+    if AUTO_SANITIZE.load(Ordering::Relaxed) {
+        log.sanitize();
+    }
+    let prefix = LOG_PREFIX.with(|prefix| *prefix);
+    if !prefix.is_empty() {
+        if log.message.try_reserve(prefix.len()).is_err() {
+            OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+            note_out_of_memory();
+            report_out_of_memory();
+            return;
+        }
+        log.message.insert_str(0, prefix);
+    }
+    let max_message_length = MAX_MESSAGE_LENGTH.with(|max| *max);
+    if let Some(max) = max_message_length {
+        truncate_message(&mut log.message, max);
+    }
+    log.sequence = NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    log.timestamp = timestamp;
+    let deduplicate = DEDUPLICATION.load(Ordering::Relaxed);
+    let committed = LOGS.with(|logs| {
+        let mut dropped = None;
+        let log = match logs.last() {
+            Some(last) if deduplicate && last.channel == log.channel && last.extra_channels == log.extra_channels && last.level == log.level && last.message == log.message => {
+                let Some(mut last) = try_clone_log(last) else {
+                    note_out_of_memory();
+                    return None;
+                };
+                last.occurrences = last.occurrences.saturating_add(1);
+                let index = logs.len() - 1;
+                logs.remove_at(index);
+                match try_clone_log(&last) {
+                    Some(stored) => logs.push(stored),
+                    None => {
+                        OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+                        note_out_of_memory();
+                    }
+                }
+                last
+            }
+            _ => {
+                let capacity = LOG_CAPACITY.with(|capacity| *capacity);
+                let overflow_handler = OVERFLOW_HANDLER.with(|handler| *handler);
+                match capacity {
+                    Some(0) => {
+                        OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+                        dropped = overflow_handler.and_then(|_| try_clone_log(&log));
+                    }
+                    Some(capacity) if logs.len() >= capacity => {
+                        OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+                        dropped = overflow_handler.and_then(|_| logs.iter().next().and_then(try_clone_log));
+                        logs.remove_at(0);
+                        push_to_store(logs, &log);
+                    }
+                    _ => push_to_store(logs, &log),
+                }
+                log
+            }
+        };
+        LOG_COUNT.store(logs.len(), Ordering::Relaxed);
+        Some((log, dropped))
+    });
+    let Some((log, dropped)) = committed else {
+        report_out_of_memory();
+        return;
+    };
+    if let Some(dropped) = dropped {
+        let overflow_handler = OVERFLOW_HANDLER.with(|handler| *handler);
+        if let Some(handler) = overflow_handler {
+            handler(&dropped);
+        }
+    }
+    with_listener_list(|listeners| {
+        for entry in listeners.iter_mut() {
+            entry.on_log(&log);
+        }
+    });
+    report_out_of_memory();
+}
+
+/// Pushes a copy of `log` to the store, dropping it and counting it as an overflow if it cannot be allocated.
+#[cfg(not(feature = "no_global"))]
+fn push_to_store(logs: &mut Box<dyn LogStore + Send>, log: &Log) {
+    match try_clone_log(log) {
+        Some(stored) => logs.push(stored),
+        None => {
+            OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+            note_out_of_memory();
+        }
+    }
+}
+
+/// Clones a log, returning `None` instead of aborting if any of its strings cannot be allocated.
+#[cfg(not(feature = "no_global"))]
+fn try_clone_log(log: &Log) -> Option<Log> {
+    fn try_clone_string(s: &str) -> Option<String> {
+        let mut clone = String::new();
+        clone.try_reserve_exact(s.len()).ok()?;
+        clone.push_str(s);
+        Some(clone)
+    }
+    let mut extra_channels = Vec::new();
+    extra_channels.try_reserve_exact(log.extra_channels.len()).ok()?;
+    for channel in &log.extra_channels {
+        extra_channels.push(try_clone_string(channel)?);
+    }
+    let metadata = match &log.metadata {
+        Some(pairs) => {
+            let mut metadata = Vec::new();
+            metadata.try_reserve_exact(pairs.len()).ok()?;
+            for (key, value) in pairs {
+                metadata.push((try_clone_string(key)?, try_clone_string(value)?));
+            }
+            Some(metadata)
+        }
+        None => None,
+    };
+    Some(Log {
+        channel: try_clone_string(&log.channel)?,
+        extra_channels,
+        message: try_clone_string(&log.message)?,
+        metadata,
+        ..*log
+    })
+}
+
+/// Reads the clock registered with `set_clock`. The clock is called with the clock unlocked, so that it may replace itself.
+#[cfg(not(feature = "no_global"))]
+fn read_clock() -> Option<u64> {
+    let clock = CLOCK.with(|clock| *clock);
+    clock.map(|clock| clock())
+}
+
+/// Records that an allocation failed. The handler registered with `set_oom_handler` is called by `report_out_of_memory`
+/// rather than here, because failures are usually noticed while the log store or the listener list is locked.
+#[cfg(not(feature = "no_global"))]
+fn note_out_of_memory() {
+    OUT_OF_MEMORY.store(true, Ordering::Relaxed);
+}
+
+/// Calls the handler registered with `set_oom_handler` if an allocation failed since the last call.
+/// This must only be called with nothing locked, so that the handler can log or read the traceback.
+#[cfg(not(feature = "no_global"))]
+fn report_out_of_memory() {
+    if !OUT_OF_MEMORY.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let handler = OOM_HANDLER.with(|handler| *handler);
+    if let Some(handler) = handler {
+        handler();
+    }
+}
+
+/// Represents a traceback of logs.
+/// `Traceback` beautifully implements `Display` for easy printing, one log per line like `to_string()`.
+/// ```rust
+/// use breadcrumbs::{Traceback, Log};
+/// let traceback = Traceback(vec![Log::new(String::from("test_channel"), breadcrumbs::LogLevel::Info, String::from("Test log message"))]);
+/// assert_eq!(format!("{}", traceback), "[test_channel/Info    ] Test log message\n");
+/// ```
+///
+/// The alternate form (`{:#}`) prints an aligned table instead, with a right-aligned column of sequence numbers
+/// (or positions, for logs without one), padded level names and channels truncated to 20 characters.
+/// ```rust
+/// use breadcrumbs::{Traceback, Log};
+/// let traceback = Traceback(vec![
+///     Log::new(String::from("test_channel"), breadcrumbs::LogLevel::Info, String::from("Test log message")),
+///     Log::new(String::from("a_very_long_channel_name"), breadcrumbs::LogLevel::Critical, String::from("Test log message")),
+///     Log::new_multi(vec![String::from("net"), String::from("security")], breadcrumbs::LogLevel::Warn, String::from("Test log message")),
+/// ]);
+/// assert_eq!(format!("{:#}", traceback), concat!(
+///     "1 Info     test_channel         Test log message\n",
+///     "2 Critical a_very_long_channel… Test log message\n",
+///     "3 Warn     net,security         Test log message\n",
+/// ));
+/// ```
+#[derive(PartialEq, Eq, Clone, Default)]
+pub struct Traceback(pub Vec<Log>);
+
+const TRACEBACK_CHANNEL_WIDTH: usize = 20;
+
+impl Traceback {
+    /// Creates an empty traceback. This is equivalent to `Traceback::default()`.
+    /// ```rust
+    /// use breadcrumbs::Traceback;
+    /// assert!(Traceback::new().is_empty());
+    /// assert_eq!(Traceback::new(), Traceback::default());
+    /// ```
+    pub fn new() -> Traceback {
+        Traceback(Vec::new())
+    }
+
+    /// Converts the traceback to a beautifully-formatted string.
+    /// ```rust
+    /// # #[cfg(not(feature = "no_global"))]
+    /// # {
+    /// use breadcrumbs::traceback;
+    /// let traceback = traceback!();
+    /// let traceback_string = traceback.to_string();
+    /// # }
+    /// ```
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        let mut traceback = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = self.write_to(&mut traceback);
+        traceback
+    }
+
+    /// Writes the traceback, one log per line, straight into any `core::fmt::Write` target such as a UART
+    /// driver or a fixed-size buffer, without allocating an intermediate string.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("test_channel"), LogLevel::Info, String::from("Test log message"))]);
+    /// let mut output = String::new();
+    /// traceback.write_to(&mut output).unwrap();
+    /// assert_eq!(output, "[test_channel/Info    ] Test log message\n");
+    /// ```
+    pub fn write_to<W: core::fmt::Write>(&self, writer: &mut W) -> core::fmt::Result {
+        for log in &self.0 {
+            writeln!(writer, "{}", log)?;
+        }
+        Ok(())
+    }
+
+    /// Converts the traceback to a string like `to_string()`, with each level label colored using ANSI escape codes:
+    /// white for `Verbose`, cyan for `Debug`, green for `Info`, yellow for `Warn`, red for `Error` and bold bright red for `Critical`.
+    /// This requires the `ansi-colors` feature.
+    /// ```rust
+    /// # #[cfg(feature = "ansi-colors")]
+    /// # {
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("net"), LogLevel::Error, String::from("connect failed"))]);
+    /// assert_eq!(traceback.to_ansi_string(), "[net/\x1b[31mError   \x1b[0m] connect failed\n");
+    /// # }
+    /// ```
+    #[cfg(feature = "ansi-colors")]
+    pub fn to_ansi_string(&self) -> String {
+        self.colorize(true).to_string()
+    }
+
+    /// Writes each log on its own line with its level label wrapped in an ANSI color code.
+    #[cfg(feature = "ansi-colors")]
+    fn fmt_ansi(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for log in &self.0 {
+            log.fmt_ansi(f)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a wrapper that displays the traceback with ANSI-colored level labels if `enabled` is `true`,
+    /// or exactly like the traceback itself otherwise, so the choice can be made once at runtime.
+    /// Both the default and the alternate (`{:#}`) table form are colored. This requires the `ansi-colors` feature.
+    /// ```rust
+    /// # #[cfg(feature = "ansi-colors")]
+    /// # {
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("net"), LogLevel::Info, String::from("Connected"))]);
+    /// let terminal_supports_color = false;
+    /// assert_eq!(traceback.colorize(terminal_supports_color).to_string(), traceback.to_string());
+    /// assert_eq!(traceback.colorize(true).to_string(), traceback.to_ansi_string());
+    /// assert_eq!(format!("{:#}", traceback.colorize(true)), "1 \x1b[32mInfo    \x1b[0m net Connected\n");
+    /// # }
+    /// ```
+    #[cfg(feature = "ansi-colors")]
+    pub fn colorize(&self, enabled: bool) -> ColorizedTraceback<'_> {
+        ColorizedTraceback { traceback: self, enabled }
+    }
+
+    /// Returns an iterator over the logs in the traceback.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("test_channel"), LogLevel::Info, String::from("Test log message"))]);
+    /// for log in traceback.iter() {
+    ///     assert_eq!(log.message, "Test log message");
+    /// }
+    /// ```
+    pub fn iter(&self) -> core::slice::Iter<'_, Log> {
+        self.0.iter()
+    }
+
+    /// Returns the logs in the traceback as a slice.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("test_channel"), LogLevel::Info, String::from("Test log message"))]);
+    /// assert_eq!(traceback.as_logs()[0].message, "Test log message");
+    /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn as_logs(&self) -> &[Log] {
+        &self.0
+    }
+
+    /// Consumes the traceback, returning its logs.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("test_channel"), LogLevel::Info, String::from("Test log message"))]);
+    /// let logs: Vec<Log> = traceback.into_logs();
+    /// assert_eq!(logs.len(), 1);
+    /// ```
+    pub fn into_logs(self) -> Vec<Log> {
+        self.0
+    }
+
+    /// Returns the number of logs in the traceback.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("test_channel"), LogLevel::Info, String::from("Test log message"))]);
+    /// assert_eq!(traceback.len(), 1);
+    /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Checks if the traceback contains no logs.
+    /// ```rust
+    /// use breadcrumbs::Traceback;
+    /// let traceback = Traceback(vec![]);
+    /// assert!(traceback.is_empty());
+    /// ```
+    #[cfg_attr(feature = "nopanic", no_panic::no_panic)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns a new traceback containing the last `n` logs, or all logs if there are fewer than `n`.
+    /// It is equivalent to `retain_last_n`.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 1")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 2")),
+    /// ]);
+    /// assert_eq!(traceback.tail(1).to_string(), "[Info    ] Log 2\n");
+    /// ```
+    pub fn tail(&self, n: usize) -> Traceback {
+        let start = self.0.len().saturating_sub(n);
+        Traceback(self.0[start..].to_vec())
+    }
+
+    /// Returns a new traceback containing the last `n` logs, or all logs if there are fewer than `n`, leaving this one untouched.
+    /// This is the idiom for showing a sliding window of recent logs, e.g. on a status page, while keeping the full history.
+    /// It is equivalent to `tail`, which reads better next to `head` and the iterator methods.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 1")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 2")),
+    /// ]);
+    /// assert_eq!(traceback.retain_last_n(1), traceback.tail(1));
+    /// assert_eq!(traceback.retain_last_n(5).len(), 2);
+    /// ```
+    pub fn retain_last_n(&self, n: usize) -> Traceback {
+        self.tail(n)
+    }
+
+    /// Returns the highest sequence number of the logs in the traceback, or `None` if it holds no stored logs.
+    /// Manually constructed logs, which have a sequence number of 0, are ignored.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut log = Log::new(String::from("net"), LogLevel::Info, String::from("Connected"));
+    /// assert_eq!(Traceback(vec![log.clone()]).max_sequence(), None);
+    /// log.sequence = 7;
+    /// assert_eq!(Traceback(vec![log]).max_sequence(), Some(7));
+    /// ```
+    pub fn max_sequence(&self) -> Option<u64> {
+        self.0.iter().map(|log| log.sequence).filter(|&sequence| sequence != 0).max()
+    }
+
+    /// Returns a new traceback containing the logs whose message contains `query`, ignoring ASCII case.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("Connection Timeout")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("Connected")),
+    /// ]);
+    /// assert_eq!(traceback.search("timeout").len(), 1);
+    /// assert_eq!(traceback.search("CONNECT").len(), 2);
+    /// ```
+    pub fn search(&self, query: &str) -> Traceback {
+        let query = query.to_ascii_lowercase();
+        Traceback(self.0.iter().filter(|log| log.message.to_ascii_lowercase().contains(&query)).cloned().collect())
+    }
+
+    /// Returns a new traceback containing the logs with a channel whose name contains `query`, ignoring ASCII case.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net/wifi"), LogLevel::Error, String::from("Connection timeout")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("Mounted")),
+    /// ]);
+    /// assert_eq!(traceback.search_channel("WIFI").len(), 1);
+    /// ```
+    pub fn search_channel(&self, query: &str) -> Traceback {
+        let query = query.to_ascii_lowercase();
+        Traceback(
+            self.0
+                .iter()
+                .filter(|log| log.channels().any(|channel| channel.to_ascii_lowercase().contains(&query)))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Returns a new traceback that keeps only the first of the logs with the same channels, level and message,
+    /// wherever they appear. Unlike `deduplicate`, repeats that are not next to each other are removed too.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("Connected")),
+    ///     Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying")),
+    /// ]);
+    /// assert_eq!(traceback.deduplicate_all().to_string(), "[net/Warn    ] Retrying\n[net/Info    ] Connected\n");
+    /// ```
+    pub fn deduplicate_all(&self) -> Traceback {
+        let mut seen = BTreeSet::new();
+        let mut logs = Vec::new();
+        for log in &self.0 {
+            if seen.insert((log.channel.as_str(), log.extra_channels.as_slice(), log.level, log.message.as_str())) {
+                logs.push(log.clone());
+            }
+        }
+        Traceback(logs)
+    }
+
+    /// Consumes the traceback, returning the set of distinct logs in it.
+    /// Logs are compared on every field, so logs with different sequence numbers or timestamps are all kept.
+    /// This requires the `std` feature.
+    /// ```rust
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let log = Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying"));
+    /// let traceback = Traceback(vec![log.clone(), log.clone()]);
+    /// assert_eq!(traceback.into_unique_set().len(), 1);
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn into_unique_set(self) -> std::collections::HashSet<Log> {
+        self.0.into_iter().collect()
+    }
+
+    /// Returns a new traceback containing the logs at indices `from` up to but not including `to`, clamped to the length of the traceback.
+    /// It is empty if `from` is not before `to` or is past the end, which makes it convenient for paging through logs.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 1")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 2")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 3")),
+    /// ]);
+    /// assert_eq!(traceback.window(1, 2).to_string(), "[Info    ] Log 2\n");
+    /// assert_eq!(traceback.window(2, 10).to_string(), "[Info    ] Log 3\n");
+    /// assert!(traceback.window(3, 5).is_empty());
+    /// assert!(traceback.window(2, 1).is_empty());
+    /// ```
+    pub fn window(&self, from: usize, to: usize) -> Traceback {
+        let to = to.min(self.0.len());
+        if from >= to {
+            return Traceback(Vec::new());
+        }
+        Traceback(self.0[from..to].to_vec())
+    }
+
+    /// Returns a new traceback that leaves out every log with the same channel, level and message as the log before it,
+    /// like `uniq(1)`. The stored logs are not changed; see `enable_deduplication` to collapse repeats as they are logged.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying")),
+    ///     Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("Connected")),
+    ///     Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying")),
+    /// ]);
+    /// assert_eq!(traceback.deduplicate().to_string(), "[net/Warn    ] Retrying\n[net/Info    ] Connected\n[net/Warn    ] Retrying\n");
+    /// ```
+    pub fn deduplicate(&self) -> Traceback {
+        let mut logs: Vec<Log> = Vec::new();
+        for log in &self.0 {
+            match logs.last() {
+                Some(last) if last.channel == log.channel && last.extra_channels == log.extra_channels && last.level == log.level && last.message == log.message => {}
+                _ => logs.push(log.clone()),
+            }
+        }
+        Traceback(logs)
+    }
+
+    /// Returns a new traceback containing the first `n` logs, or all logs if there are fewer than `n`.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 1")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 2")),
+    /// ]);
+    /// assert_eq!(traceback.head(1).to_string(), "[Info    ] Log 1\n");
+    /// ```
+    pub fn head(&self, n: usize) -> Traceback {
+        let end = n.min(self.0.len());
+        Traceback(self.0[..end].to_vec())
+    }
+
+    /// Sorts the logs in the traceback by their sequence number, restoring the order they were logged in.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut first = Log::new(String::from(""), LogLevel::Info, String::from("Log 1"));
+    /// first.sequence = 1;
+    /// let mut second = Log::new(String::from(""), LogLevel::Info, String::from("Log 2"));
+    /// second.sequence = 2;
+    /// let mut traceback = Traceback(vec![second, first]);
+    /// traceback.sort_by_sequence();
+    /// assert_eq!(traceback.to_string(), "[Info    ] Log 1\n[Info    ] Log 2\n");
+    /// ```
+    pub fn sort_by_sequence(&mut self) {
+        self.0.sort_by_key(|log| log.sequence);
+    }
+
+    /// Sorts the logs in the traceback by severity, placing the most severe logs first.
+    /// Logs of the same level keep their order.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut traceback = Traceback(vec![
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 1")),
+    ///     Log::new(String::from(""), LogLevel::Critical, String::from("Log 2")),
+    /// ]);
+    /// traceback.sort_by_level();
+    /// assert_eq!(traceback.to_string(), "[Critical] Log 2\n[Info    ] Log 1\n");
+    /// ```
+    pub fn sort_by_level(&mut self) {
+        self.0.sort_by_key(|log| core::cmp::Reverse(log.level));
+    }
+
+    /// Sorts the logs in the traceback by severity, placing the least severe logs first.
+    /// Logs of the same level keep their order.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut traceback = Traceback(vec![
+    ///     Log::new(String::from(""), LogLevel::Critical, String::from("Log 1")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("Log 2")),
+    /// ]);
+    /// traceback.sort_by_level_asc();
+    /// assert_eq!(traceback.to_string(), "[Info    ] Log 2\n[Critical] Log 1\n");
+    /// ```
+    pub fn sort_by_level_asc(&mut self) {
+        self.0.sort_by_key(|log| log.level);
+    }
+
+    /// Sorts the logs in the traceback alphabetically by channel.
+    /// Logs in the same channel keep their order.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("Log 1")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("Log 2")),
+    /// ]);
+    /// traceback.sort_by_channel();
+    /// assert_eq!(traceback.to_string(), "[disk/Info    ] Log 2\n[net/Info    ] Log 1\n");
+    /// ```
+    pub fn sort_by_channel(&mut self) {
+        self.0.sort_by(|a, b| a.channel.cmp(&b.channel));
+    }
+
+    /// Sorts the logs in the traceback by their timestamp, placing logs without a timestamp first.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut first = Log::new(String::from(""), LogLevel::Info, String::from("Log 1"));
+    /// first.timestamp = Some(10);
+    /// let mut second = Log::new(String::from(""), LogLevel::Info, String::from("Log 2"));
+    /// second.timestamp = Some(20);
+    /// let mut traceback = Traceback(vec![second, first]);
+    /// traceback.sort_by_timestamp();
+    /// assert_eq!(traceback.to_string(), "[10/Info    ] Log 1\n[20/Info    ] Log 2\n");
+    /// ```
+    pub fn sort_by_timestamp(&mut self) {
+        self.0.sort_by_key(|log| log.timestamp);
+    }
+
+    /// Compares this traceback, taken as the baseline, with a later snapshot.
+    /// Logs are matched by sequence number when they have one, and by equality otherwise.
+    /// ```rust
+    /// # #[cfg(not(feature = "no_global"))]
+    /// # {
+    /// use breadcrumbs::{init, log, traceback};
+    /// init!();
+    /// log!("Before");
+    /// let before = traceback!();
+    /// log!("After");
+    /// let diff = before.diff(&traceback!());
+    /// assert_eq!(diff.added.len(), 1);
+    /// assert_eq!(diff.added[0].message, "After");
+    /// assert!(diff.removed.is_empty());
+    /// # }
+    /// ```
+    pub fn diff(&self, other: &Traceback) -> TracebackDiff {
+        fn contains(logs: &[Log], target: &Log) -> bool {
+            if target.sequence != 0 {
+                logs.iter().any(|log| log.sequence == target.sequence)
+            } else {
+                logs.contains(target)
+            }
+        }
+        TracebackDiff {
+            added: other.0.iter().filter(|log| !contains(&self.0, log)).cloned().collect(),
+            removed: self.0.iter().filter(|log| !contains(&other.0, log)).cloned().collect(),
+        }
+    }
+
+    /// Returns a new traceback containing only the logs matching the predicate.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect timeout")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("connected")),
+    /// ]);
+    /// assert_eq!(traceback.filter(|log| log.message.contains("timeout")).len(), 1);
+    /// ```
+    pub fn filter<F: Fn(&Log) -> bool>(&self, predicate: F) -> Traceback {
+        Traceback(self.0.iter().filter(|log| predicate(log)).cloned().collect())
+    }
+
+    /// Returns a new traceback with the function applied to each log.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("net"), LogLevel::Error, String::from("connect timeout"))]);
+    /// let traceback = traceback.map(|mut log| { log.channel = "filtered".into(); log });
+    /// assert_eq!(traceback.0[0].channel, "filtered");
+    /// ```
+    pub fn map<F: Fn(Log) -> Log>(self, f: F) -> Traceback {
+        Traceback(self.0.into_iter().map(f).collect())
+    }
+
+    /// Concatenates two tracebacks, keeping every log of `a` followed by every log of `b`, including duplicates.
+    /// The `+` and `+=` operators do the same.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let before = Traceback(vec![Log::new(String::from(""), LogLevel::Info, String::from("Log 1"))]);
+    /// let after = Traceback(vec![Log::new(String::from(""), LogLevel::Info, String::from("Log 2"))]);
+    /// let report = Traceback::merge(before.clone(), after.clone());
+    /// assert_eq!(report.to_string(), "[Info    ] Log 1\n[Info    ] Log 2\n");
+    /// assert_eq!(before + after, report);
+    /// ```
+    pub fn merge(a: Traceback, b: Traceback) -> Traceback {
+        let mut merged = a;
+        merged.extend(b);
+        merged
+    }
+
+    /// Returns a new traceback containing the logs made more than `ticks` ticks ago, according to the clock registered by `set_clock`.
+    /// Logs without a timestamp are left out, as are all logs if no clock is registered. Useful for finding stale logs to remove.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel, set_clock, clear_clock};
+    /// fn ticks() -> u64 {
+    ///     1000
+    /// }
+    ///
+    /// let mut old = Log::new(String::from(""), LogLevel::Info, String::from("Log 1"));
+    /// old.timestamp = Some(100);
+    /// let mut recent = Log::new(String::from(""), LogLevel::Info, String::from("Log 2"));
+    /// recent.timestamp = Some(950);
+    /// set_clock(ticks);
+    /// assert_eq!(Traceback(vec![old, recent]).logs_older_than(500).to_string(), "[100/Info    ] Log 1\n");
+    /// clear_clock();
+    /// ```
+    #[cfg(not(feature = "no_global"))]
+    pub fn logs_older_than(&self, ticks: u64) -> Traceback {
+        let now = match read_clock() {
+            Some(now) => now,
+            None => return Traceback(Vec::new()),
+        };
+        self.filter(|log| log.timestamp.is_some_and(|timestamp| now.saturating_sub(timestamp) > ticks))
+    }
+
+    /// Counts the logs in the traceback per level, returning a `(level, count)` pair for every level in ascending order of severity.
+    /// This works on the traceback alone, so unlike calling `log_count_level` once per level it does not lock the log store.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")),
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed again")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("mounted")),
+    /// ]);
+    /// let counts = traceback.count_by_level();
+    /// assert_eq!(counts[0], (LogLevel::Verbose, 0));
+    /// assert_eq!(counts[2], (LogLevel::Info, 1));
+    /// assert_eq!(counts[4], (LogLevel::Error, 2));
+    /// ```
+    pub fn count_by_level(&self) -> [(LogLevel, usize); ALL_LOG_LEVELS.len()] {
+        let mut counts = ALL_LOG_LEVELS.map(|level| (level, 0));
+        for log in &self.0 {
+            counts[log.level.numeric() as usize].1 += 1;
+        }
+        counts
+    }
+
+    /// Counts the logs in the traceback per channel, returning `(channel, count)` pairs sorted by channel name.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("mounted")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("retrying")),
+    /// ]);
+    /// assert_eq!(traceback.count_by_channel(), vec![(String::from("disk"), 1), (String::from("net"), 2)]);
+    /// ```
+    pub fn count_by_channel(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for log in &self.0 {
+            *counts.entry(log.channel.as_str()).or_insert(0) += 1;
+        }
+        counts.into_iter().map(|(channel, count)| (String::from(channel), count)).collect()
+    }
+
+    /// Returns the distinct channels of the logs in the traceback, in order of first appearance.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("mounted")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("retrying")),
+    /// ]);
+    /// assert_eq!(traceback.channels(), vec!["net", "disk"]);
+    /// ```
+    pub fn channels(&self) -> Vec<String> {
+        let mut channels: Vec<String> = Vec::new();
+        for log in &self.0 {
+            if !channels.contains(&log.channel) {
+                channels.push(log.channel.clone());
+            }
+        }
+        channels
+    }
+
+    /// Splits the traceback into one traceback per channel, in order of each channel's first appearance.
+    /// The logs of each channel keep their order.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("mounted")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("retrying")),
+    /// ]);
+    /// let groups = traceback.group_by_channel();
+    /// assert_eq!(groups.len(), 2);
+    /// assert_eq!(groups[0].0, "net");
+    /// assert_eq!(groups[0].1.len(), 2);
+    /// ```
+    pub fn group_by_channel(&self) -> Vec<(String, Traceback)> {
+        let mut groups: Vec<(String, Traceback)> = Vec::new();
+        for log in &self.0 {
+            match groups.iter_mut().find(|(channel, _)| *channel == log.channel) {
+                Some((_, group)) => group.0.push(log.clone()),
+                None => groups.push((log.channel.clone(), Traceback(vec![log.clone()]))),
+            }
+        }
+        groups
+    }
+
+    /// Converts the traceback to a string with the logs grouped by channel, each group under a `=== channel ===` header.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")),
+    ///     Log::new(String::from("disk"), LogLevel::Info, String::from("mounted")),
+    ///     Log::new(String::from("net"), LogLevel::Info, String::from("retrying")),
+    /// ]);
+    /// assert_eq!(
+    ///     traceback.fmt_grouped(),
+    ///     "=== net ===\n[net/Error   ] connect failed\n[net/Info    ] retrying\n=== disk ===\n[disk/Info    ] mounted\n"
+    /// );
+    /// ```
+    pub fn fmt_grouped(&self) -> String {
+        let mut grouped = String::new();
+        for (channel, group) in self.group_by_channel() {
+            grouped.push_str(&format!("=== {} ===\n", channel));
+            grouped.push_str(&group.to_string());
+        }
+        grouped
+    }
+
+    /// Converts the traceback to a compact single-line string, for targets where newlines are expensive.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![
+    ///     Log::new(String::from("net"), LogLevel::Error, String::from("connect failed")),
+    ///     Log::new(String::from(""), LogLevel::Info, String::from("retrying\nsoon")),
+    /// ]);
+    /// assert_eq!(traceback.fmt_compact(), r#"[{channel:"net",level:Error,message:"connect failed"},{channel:"",level:Info,message:"retrying\nsoon"}]"#);
+    /// ```
+    pub fn fmt_compact(&self) -> String {
+        let mut compact = String::from("[");
+        for (i, log) in self.0.iter().enumerate() {
+            if i > 0 {
+                compact.push(',');
+            }
+            compact.push_str(&format!("{{channel:{:?},level:{},message:{:?}}}", log.channel, log.level, log.message));
+        }
+        compact.push(']');
+        compact
+    }
+
+    /// Converts the traceback to a JSON array of log objects, without requiring serde.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback(vec![Log::new(String::from("net"), LogLevel::Error, String::from("said \"hi\""))]);
+    /// assert_eq!(traceback.to_json(), r#"[{"channel":"net","level":"Error","message":"said \"hi\""}]"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[");
+        for (i, log) in self.0.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"channel\":");
+            push_json_string(&mut json, &log.channel);
+            json.push_str(",\"level\":");
+            push_json_string(&mut json, &log.level.to_string());
+            json.push_str(",\"message\":");
+            push_json_string(&mut json, &log.message);
+            json.push('}');
+        }
+        json.push(']');
+        json
+    }
+
+    /// Parses a traceback from the JSON format produced by `to_json`.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let traceback = Traceback::from_json(r#"[{"channel":"net","level":"Error","message":"connect failed"}]"#).unwrap();
+    /// assert_eq!(traceback.0, vec![Log::new(String::from("net"), LogLevel::Error, String::from("connect failed"))]);
+    /// ```
+    pub fn from_json(s: &str) -> Result<Traceback, JsonParseError> {
+        let mut parser = JsonParser { input: s, position: 0 };
+        let mut logs = Vec::new();
+        parser.expect(b'[')?;
+        if parser.peek() == Some(b']') {
+            parser.position += 1;
+        } else {
+            loop {
+                logs.push(parser.parse_log()?);
+                match parser.next()? {
+                    b',' => continue,
+                    b']' => break,
+                    _ => return Err(JsonParseError::UnexpectedCharacter(parser.position - 1)),
+                }
+            }
+        }
+        if parser.peek().is_some() {
+            return Err(JsonParseError::UnexpectedCharacter(parser.position));
+        }
+        Ok(Traceback(logs))
+    }
+
+    /// Converts the traceback to CSV with a `sequence,timestamp,channel,level,message` header, for opening in a spreadsheet.
+    /// Fields are quoted as described in RFC 4180, and a missing sequence number or timestamp is left empty.
+    /// ```rust
+    /// use breadcrumbs::{Traceback, Log, LogLevel};
+    /// let mut log = Log::new(String::from("net"), LogLevel::Error, String::from("said \"hi\", then left"));
+    /// log.sequence = 7;
+    /// let traceback = Traceback(vec![log]);
+    /// assert_eq!(traceback.to_csv(), "sequence,timestamp,channel,level,message\r\n7,,net,Error,\"said \"\"hi\"\", then left\"\r\n");
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("sequence,timestamp,channel,level,message\r\n");
+        for log in &self.0 {
+            if log.sequence != 0 {
+                csv.push_str(&log.sequence.to_string());
+            }
+            csv.push(',');
+            if let Some(timestamp) = log.timestamp {
+                csv.push_str(&timestamp.to_string());
+            }
+            csv.push(',');
+            push_csv_field(&mut csv, &log.channel);
+            csv.push(',');
+            push_csv_field(&mut csv, &log.level.to_string());
+            csv.push(',');
+            push_csv_field(&mut csv, &log.message);
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+/// Appends `s` to `csv` as a field, quoting it if it contains a comma, double quote or line break.
+fn push_csv_field(csv: &mut String, s: &str) {
+    if s.contains([',', '"', '\r', '\n']) {
+        csv.push('"');
+        csv.push_str(&s.replace('"', "\"\""));
+        csv.push('"');
+    } else {
+        csv.push_str(s);
+    }
+}
+
+/// Appends `s` to `json` as a quoted and escaped JSON string.
+fn push_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// The error returned when parsing a `Traceback` from JSON fails.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum JsonParseError {
+    /// The input ended before the traceback was complete.
+    UnexpectedEnd,
+    /// An unexpected character was found at the given byte offset.
+    UnexpectedCharacter(usize),
+    /// An invalid escape sequence was found at the given byte offset.
+    InvalidEscape(usize),
+    /// A log object contained a field other than `channel`, `level` and `message`.
+    UnknownField(String),
+    /// A log object was missing one of the `channel`, `level` and `message` fields.
+    MissingField(&'static str),
+    /// A log object contained an unknown level.
+    UnknownLevel(String),
+}
+
+impl core::fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            JsonParseError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            JsonParseError::UnexpectedCharacter(position) => write!(f, "unexpected character at position {}", position),
+            JsonParseError::InvalidEscape(position) => write!(f, "invalid escape sequence at position {}", position),
+            JsonParseError::UnknownField(field) => write!(f, "unknown field: {}", field),
+            JsonParseError::MissingField(field) => write!(f, "missing field: {}", field),
+            JsonParseError::UnknownLevel(level) => write!(f, "unknown log level: {}", level),
+        }
+    }
+}
+
+/// A minimal parser for the JSON format produced by `Traceback::to_json`.
+struct JsonParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.as_bytes().get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, JsonParseError> {
+        let byte = self.peek().ok_or(JsonParseError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), JsonParseError> {
+        match self.next()? {
+            byte if byte == expected => Ok(()),
+            _ => Err(JsonParseError::UnexpectedCharacter(self.position - 1)),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonParseError> {
+        self.expect(b'"')?;
+        let mut string = String::new();
+        let mut chars = self.input[self.position..].char_indices();
+        loop {
+            let (offset, c) = chars.next().ok_or(JsonParseError::UnexpectedEnd)?;
+            match c {
+                '"' => {
+                    self.position += offset + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escape_position = self.position + offset;
+                    let (_, escaped) = chars.next().ok_or(JsonParseError::UnexpectedEnd)?;
+                    match escaped {
+                        '"' => string.push('"'),
+                        '\\' => string.push('\\'),
+                        '/' => string.push('/'),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'u' => {
+                            let mut code = 0;
+                            for _ in 0..4 {
+                                let (_, digit) = chars.next().ok_or(JsonParseError::UnexpectedEnd)?;
+                                code = code * 16 + digit.to_digit(16).ok_or(JsonParseError::InvalidEscape(escape_position))?;
+                            }
+                            string.push(char::from_u32(code).ok_or(JsonParseError::InvalidEscape(escape_position))?);
+                        }
+                        _ => return Err(JsonParseError::InvalidEscape(escape_position)),
+                    }
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn parse_log(&mut self) -> Result<Log, JsonParseError> {
+        let mut channel = None;
+        let mut level = None;
+        let mut message = None;
+        self.expect(b'{')?;
+        loop {
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            let value = self.parse_string()?;
+            match key.as_str() {
+                "channel" => channel = Some(value),
+                "level" => level = Some(LogLevel::try_from_str(&value).map_err(|_| JsonParseError::UnknownLevel(value))?),
+                "message" => message = Some(value),
+                _ => return Err(JsonParseError::UnknownField(key)),
+            }
+            match self.next()? {
+                b',' => continue,
+                b'}' => break,
+                _ => return Err(JsonParseError::UnexpectedCharacter(self.position - 1)),
+            }
+        }
+        Ok(Log::new(
+            channel.ok_or(JsonParseError::MissingField("channel"))?,
+            level.ok_or(JsonParseError::MissingField("level"))?,
+            message.ok_or(JsonParseError::MissingField("message"))?,
+        ))
+    }
+}
+
+impl core::fmt::Display for Traceback {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if f.alternate() {
+            return self.fmt_table(f, false);
+        }
+        self.write_to(f)
+    }
+}
+
+/// A traceback displayed with ANSI-colored level labels, created with `Traceback::colorize()`.
+/// This requires the `ansi-colors` feature.
+#[cfg(feature = "ansi-colors")]
+pub struct ColorizedTraceback<'a> {
+    traceback: &'a Traceback,
+    enabled: bool,
+}
+
+#[cfg(feature = "ansi-colors")]
+impl core::fmt::Display for ColorizedTraceback<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match (self.enabled, f.alternate()) {
+            (false, _) => core::fmt::Display::fmt(self.traceback, f),
+            (true, false) => self.traceback.fmt_ansi(f),
+            (true, true) => self.traceback.fmt_table(f, true),
+        }
+    }
+}
+
+impl Traceback {
+    /// Writes the traceback as an aligned table, with the level labels wrapped in ANSI color codes if `colorize` is `true`.
+    #[cfg_attr(not(feature = "ansi-colors"), allow(unused_variables))]
+    fn fmt_table(&self, f: &mut core::fmt::Formatter, colorize: bool) -> core::fmt::Result {
+        let number = |index: usize, log: &Log| if log.sequence != 0 { log.sequence } else { index as u64 + 1 };
+        let digits = |mut n: u64| {
+            let mut digits = 1;
+            while n >= 10 {
+                n /= 10;
+                digits += 1;
+            }
+            digits
+        };
+        let channels: Vec<String> = self.0.iter().map(|log| log.channels().collect::<Vec<&str>>().join(",")).collect();
+        let number_width = self.0.iter().enumerate().map(|(i, log)| digits(number(i, log))).max().unwrap_or(1);
+        let timestamp_width = self.0.iter().filter_map(|log| log.timestamp).map(digits).max();
+        let channel_width = channels.iter().map(|channel| channel.chars().count()).max().unwrap_or(0).min(TRACEBACK_CHANNEL_WIDTH);
+        for (i, (log, channel)) in self.0.iter().zip(&channels).enumerate() {
+            write!(f, "{:>width$} ", number(i, log), width = number_width)?;
+            if let Some(timestamp_width) = timestamp_width {
+                match log.timestamp {
+                    Some(timestamp) => write!(f, "{:>width$} ", timestamp, width = timestamp_width)?,
+                    None => write!(f, "{:>width$} ", "", width = timestamp_width)?,
+                }
+            }
+            #[cfg(feature = "ansi-colors")]
+            if colorize {
+                write!(f, "{}{}{} ", log.level.color_code(), log.level.label_padded(), LogLevel::RESET_CODE)?;
+            } else {
+                write!(f, "{} ", log.level.label_padded())?;
+            }
+            #[cfg(not(feature = "ansi-colors"))]
+            write!(f, "{} ", log.level.label_padded())?;
+            if channel_width > 0 {
+                if channel.chars().count() > TRACEBACK_CHANNEL_WIDTH {
+                    let truncated: String = channel.chars().take(TRACEBACK_CHANNEL_WIDTH - 1).collect();
+                    write!(f, "{}… ", truncated)?;
+                } else {
+                    write!(f, "{:<width$} ", channel, width = channel_width)?;
+                }
+            }
+            log.fmt_body(f)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for Traceback {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Traceback").field("logs", &self.0).finish()
+    }
+}
+
+impl core::ops::Add<Traceback> for Traceback {
+    type Output = Traceback;
+
+    fn add(self, other: Traceback) -> Traceback {
+        Traceback::merge(self, other)
+    }
+}
+
+impl core::ops::AddAssign<Traceback> for Traceback {
+    fn add_assign(&mut self, other: Traceback) {
+        *self = Traceback::merge(Traceback(core::mem::take(&mut self.0)), other);
+    }
+}
+
+impl From<Vec<Log>> for Traceback {
+    fn from(logs: Vec<Log>) -> Traceback {
+        Traceback(logs)
+    }
+}
+
+impl From<&[Log]> for Traceback {
+    fn from(logs: &[Log]) -> Traceback {
+        Traceback(logs.to_vec())
+    }
+}
+
+impl Extend<Log> for Traceback {
+    fn extend<I: IntoIterator<Item = Log>>(&mut self, logs: I) {
+        self.0.extend(logs);
+    }
+}
+
+impl FromIterator<Log> for Traceback {
+    fn from_iter<I: IntoIterator<Item = Log>>(logs: I) -> Traceback {
+        Traceback(logs.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Traceback {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Traceback {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Traceback, D::Error> {
+        Vec::<Log>::deserialize(deserializer).map(Traceback)
+    }
+}
+
+impl IntoIterator for Traceback {
+    type Item = Log;
+    type IntoIter = alloc::vec::IntoIter<Log>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Traceback {
+    type Item = &'a Log;
+    type IntoIter = core::slice::Iter<'a, Log>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// The difference between two tracebacks, as returned by `Traceback::diff()`.
+/// `TracebackDiff` implements `Display`, prefixing removed logs with `-` and added logs with `+`.
+/// ```rust
+/// use breadcrumbs::{TracebackDiff, Log, LogLevel};
+/// let diff = TracebackDiff {
+///     added: vec![Log::new(String::from("net"), LogLevel::Info, String::from("Connected"))],
+///     removed: vec![Log::new(String::from("net"), LogLevel::Warn, String::from("Retrying"))],
+/// };
+/// assert_eq!(diff.to_string(), "- [net/Warn    ] Retrying\n+ [net/Info    ] Connected\n");
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct TracebackDiff {
+    /// The logs present in the new snapshot but not in the baseline.
+    pub added: Vec<Log>,
+    /// The logs present in the baseline but not in the new snapshot.
+    pub removed: Vec<Log>,
+}
+
+impl TracebackDiff {
+    /// Returns `true` if the two tracebacks contained the same logs.
+    /// ```rust
+    /// use breadcrumbs::Traceback;
+    /// let traceback = Traceback(vec![]);
+    /// assert!(traceback.diff(&traceback.clone()).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl core::fmt::Display for TracebackDiff {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for log in &self.removed {
+            writeln!(f, "- {}", log)?;
+        }
+        for log in &self.added {
+            writeln!(f, "+ {}", log)?;
+        }
+        Ok(())
+    }
+}
+
+/// Retrieves a traceback of logs based on the minimum log level, channel and metadata filter.
+/// The metadata filter matches logs whose metadata contains the given key-value pair.
+/// If `max_results` is given, at most that many logs are collected, keeping the oldest logs that match.
+/// Note that the `traceback!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{get_logs_traceback, LogLevel};
+/// let traceback = get_logs_traceback(Some(LogLevel::Warn), Some(vec![String::from("test_channel")]), None, None);
+/// let traceback = get_logs_traceback(None, None, Some(("port", "443")), None);
+/// let traceback = get_logs_traceback(None, None, None, Some(10));
+/// assert!(traceback.len() <= 10);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_logs_traceback(min_level: impl Into<LevelFilter>, channels: Option<Vec<String>>, metadata_filter: Option<(&str, &str)>, max_results: Option<usize>) -> Traceback {
+    let min_level = min_level.into();
+    let channels: Option<Vec<&str>> = channels.as_ref().map(|channels| channels.iter().map(String::as_str).collect());
+    let mut logs = Vec::new();
+    LOGS.with(|stored| {
+        for log in stored.iter() {
+            if let Some(max) = max_results {
+                if logs.len() >= max {
+                    break;
+                }
+            }
+            if !log.matches_filter(min_level, channels.as_deref()) {
+                continue;
+            }
+            if let Some((key, value)) = metadata_filter {
+                if !log.has_meta(key, value) {
+                    continue;
+                }
+            }
+            logs.push(log.clone());
+        }
+    });
+    Traceback(logs)
+}
+
+/// Retrieves a traceback of logs based on the minimum log level and a set of channels.
+/// A log is included if its channel is any of the given channels.
+/// Note that the `traceback_channels!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{get_logs_traceback_multi, LogLevel};
+/// let traceback = get_logs_traceback_multi(Some(LogLevel::Warn), &["sensor", "actuator"]);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_logs_traceback_multi(min_level: impl Into<LevelFilter>, channels: &[&str]) -> Traceback {
+    let min_level = min_level.into();
+    let mut logs = Vec::new();
+    LOGS.with(|stored| {
+        for log in stored.iter() {
+            if log.matches_filter(min_level, Some(channels)) {
+                logs.push(log.clone());
+            }
+        }
+    });
+    Traceback(logs)
+}
+
+/// Retrieves a traceback of logs based on the minimum log level, leaving out the logs in any of the excluded channels.
+/// To combine an allowlist with an exclusion list, chain `Traceback::filter` onto the result of `get_logs_traceback`.
+/// Note that the `traceback_excluding!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init, log, get_logs_traceback_excluding, LogLevel};
+/// init!();
+/// log!(LogLevel::Info, "noisy", "Tick");
+/// log!(LogLevel::Info, "motor", "Started");
+/// let traceback = get_logs_traceback_excluding(None, Some(vec![String::from("noisy")]));
+/// assert_eq!(traceback.len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_logs_traceback_excluding(min_level: impl Into<LevelFilter>, excluded_channels: Option<Vec<String>>) -> Traceback {
+    let min_level = min_level.into();
+    let mut logs = Vec::new();
+    LOGS.with(|stored| {
+        for log in stored.iter() {
+            if !log.matches_filter(min_level, None) {
+                continue;
+            }
+            if let Some(excluded_channels) = &excluded_channels {
+                if excluded_channels.iter().any(|channel| log.in_channel(channel)) {
+                    continue;
+                }
+            }
+            logs.push(log.clone());
+        }
+    });
+    Traceback(logs)
+}
+
+/// Retrieves a traceback of the stored logs with a sequence number greater than `sequence`, for polling new logs incrementally.
+/// Pass `0` to get every stored log, then pass the `max_sequence()` of the previous result on each poll.
+/// ```rust
+/// use breadcrumbs::{get_logs_traceback_since, log};
+/// log!("First");
+/// let seen = get_logs_traceback_since(0).max_sequence().unwrap();
+/// log!("Second");
+/// let new_logs = get_logs_traceback_since(seen);
+/// assert_eq!(new_logs.len(), 1);
+/// assert_eq!(new_logs.0[0].message, "Second");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_logs_traceback_since(sequence: u64) -> Traceback {
+    Traceback(LOGS.with(|logs| logs.iter().filter(|log| log.sequence > sequence).cloned().collect()))
+}
+
+/// Retrieves a traceback of the stored logs with a sequence number between `start` and `end` (inclusive),
+/// optionally filtered by minimum log level and channel.
+/// Note that the `traceback_between!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{get_logs_traceback_between, last_log, log, LogLevel};
+/// log!("Before");
+/// let start = last_log!().unwrap().sequence + 1;
+/// log!(LogLevel::Warn, "net", "Link down");
+/// log!(LogLevel::Info, "net", "Link up");
+/// let end = last_log!().unwrap().sequence;
+/// log!("After");
+/// assert_eq!(get_logs_traceback_between(start, end, None, None).len(), 2);
+/// assert_eq!(get_logs_traceback_between(start, end, Some(LogLevel::Warn), Some(vec![String::from("net")])).len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_logs_traceback_between(start: u64, end: u64, min_level: impl Into<LevelFilter>, channels: Option<Vec<String>>) -> Traceback {
+    let min_level = min_level.into();
+    let channels: Option<Vec<&str>> = channels.as_ref().map(|channels| channels.iter().map(String::as_str).collect());
+    let mut logs = Vec::new();
+    LOGS.with(|stored| {
+        for log in stored.iter() {
+            if log.sequence < start || log.sequence > end {
+                continue;
+            }
+            if log.matches_filter(min_level, channels.as_deref()) {
+                logs.push(log.clone());
+            }
+        }
+    });
+    Traceback(logs)
+}
+
+/// Retrieves a traceback of logs whose level is between `min` and `max` (inclusive), optionally filtered by channel.
+/// Passing a `min` that is more severe than `max` returns an empty traceback.
+/// Note that the `traceback_range!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{get_logs_traceback_range, LogLevel};
+/// let traceback = get_logs_traceback_range(LogLevel::Warn, LogLevel::Error, None);
+/// let traceback = get_logs_traceback_range(LogLevel::Warn, LogLevel::Error, Some(vec![String::from("test_channel")]));
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_logs_traceback_range(min: LogLevel, max: LogLevel, channels: Option<Vec<String>>) -> Traceback {
+    let mut logs = Vec::new();
+    LOGS.with(|stored| {
+        for log in stored.iter() {
+            if !log.level.is_at_least(min) || log.level > max {
+                continue;
+            }
+            if let Some(channels) = &channels {
+                if !channels.iter().any(|channel| log.in_channel(channel)) {
+                    continue;
+                }
+            }
+            logs.push(log.clone());
+        }
+    });
+    Traceback(logs)
+}
+
+/// Removes the logs matching the minimum log level and channel filter from the store, returning them as a traceback.
+/// The store stays locked for the whole operation, so no log can arrive between the snapshot and the removal.
+/// ```rust
+/// use breadcrumbs::{init, log, drain_logs, traceback, LogLevel};
+/// init!();
+/// log!(LogLevel::Warn, "net", "Test log message");
+/// log!(LogLevel::Info, "net", "Test log message");
+/// let drained = drain_logs(Some(LogLevel::Warn), Some(vec![String::from("net")]));
+/// assert_eq!(drained.len(), 1);
+/// assert_eq!(traceback!().len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn drain_logs(min_level: impl Into<LevelFilter>, channels: Option<Vec<String>>) -> Traceback {
+    let min_level = min_level.into();
+    let channels: Option<Vec<&str>> = channels.as_ref().map(|channels| channels.iter().map(String::as_str).collect());
+    Traceback(LOGS.with(|logs| {
+        let mut drained = Vec::new();
+        let mut indices = Vec::new();
+        for (index, log) in logs.iter().enumerate() {
+            if log.matches_filter(min_level, channels.as_deref()) {
+                drained.push(log.clone());
+                indices.push(index);
+            }
+        }
+        if indices.len() == logs.len() {
+            logs.clear();
+        } else {
+            for index in indices.into_iter().rev() {
+                logs.remove_at(index);
+            }
+        }
+        LOG_COUNT.store(logs.len(), Ordering::Relaxed);
+        drained
+    }))
+}
+
+/// Removes all logs from the store, returning them as a traceback.
+/// ```rust
+/// use breadcrumbs::{init, log, drain_all_logs, traceback};
+/// init!();
+/// log!("Test log message");
+/// assert_eq!(drain_all_logs().len(), 1);
+/// assert!(traceback!().is_empty());
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn drain_all_logs() -> Traceback {
+    drain_logs(None, None)
+}
+
+/// Copies the stored logs, oldest first, into a pre-allocated buffer, returning how many were written.
+/// Logs that do not fit in the buffer are skipped. Useful for snapshotting logs into a reserved region
+/// of memory before a reset or a firmware update.
+/// ```rust
+/// use breadcrumbs::{init, log, copy_logs_to_slice, Log, LogLevel};
+/// init!();
+/// log!("Log 1");
+/// log!("Log 2");
+/// log!("Log 3");
+/// let mut buf: [Log; 2] = core::array::from_fn(|_| Log::new(String::new(), LogLevel::Info, String::new()));
+/// assert_eq!(copy_logs_to_slice(&mut buf), 2);
+/// assert_eq!(buf[1].message, "Log 2");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn copy_logs_to_slice(buf: &mut [Log]) -> usize {
+    LOGS.with(|logs| {
+        let mut written = 0;
+        for (slot, log) in buf.iter_mut().zip(logs.iter()) {
+            slot.clone_from(log);
+            written += 1;
+        }
+        written
+    })
+}
+
+/// Counts the stored logs matching the minimum log level and channel filter, without cloning any of them.
+/// ```rust
+/// use breadcrumbs::{init, log, log_count, LogLevel};
+/// init!();
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// assert_eq!(log_count(Some(LogLevel::Error), Some(&["motor"])), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn log_count(min_level: impl Into<LevelFilter>, channels: Option<&[&str]>) -> usize {
+    let min_level = min_level.into();
+    LOGS.with(|logs| logs.iter().filter(|log| log.matches_filter(min_level, channels)).count())
+}
+
+/// Counts the stored logs that are at least as severe as the given level.
+/// ```rust
+/// use breadcrumbs::{init, log, log_count_level, LogLevel};
+/// init!();
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// assert_eq!(log_count_level(LogLevel::Warn), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn log_count_level(level: LogLevel) -> usize {
+    log_count(Some(level), None)
+}
+
+/// Counts the stored logs on the given channel.
+/// ```rust
+/// use breadcrumbs::{init, log, log_count_channel, LogLevel};
+/// init!();
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// assert_eq!(log_count_channel("motor"), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn log_count_channel(channel: &str) -> usize {
+    log_count(None, Some(&[channel]))
+}
+
+/// Retrieves the most recent stored log that is at least as severe as the minimum log level.
+/// Note that the `last_log!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init, log, get_last_log, LogLevel};
+/// init!();
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// log!(LogLevel::Info, "motor", "Retrying");
+/// assert_eq!(get_last_log(None).unwrap().message, "Retrying");
+/// assert_eq!(get_last_log(Some(LogLevel::Warn)).unwrap().message, "Stalled");
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_last_log(min_level: impl Into<LevelFilter>) -> Option<Log> {
+    let min_level = min_level.into();
+    LOGS.with(|logs| logs.iter().rev().find(|log| log.matches_filter(min_level, None)).cloned())
+}
+
+/// Retrieves the most recent stored log in the given channel that is at least as severe as the minimum log level.
+/// Note that the `last_log_channel!` macro is the preferred method to do this in the public API.
+/// ```rust
+/// use breadcrumbs::{init, log, get_last_log_for_channel, LogLevel};
+/// init!();
+/// log!(LogLevel::Error, "motor", "Stalled");
+/// log!(LogLevel::Info, "sensor", "Calibrated");
+/// assert_eq!(get_last_log_for_channel("motor", None).unwrap().message, "Stalled");
+/// assert_eq!(get_last_log_for_channel("sensor", Some(LogLevel::Warn)), None);
+/// ```
+#[cfg(not(feature = "no_global"))]
+pub fn get_last_log_for_channel(channel: &str, min_level: impl Into<LevelFilter>) -> Option<Log> {
+    let min_level = min_level.into();
+    LOGS.with(|logs| logs.iter().rev().find(|log| log.matches_filter(min_level, Some(&[channel]))).cloned())
+}
+
+/// An independent logger with its own log store, listener and settings, for subsystems that need
+/// logs kept apart from the global ones or code that must avoid global mutable state.
+/// With the `no_global` feature enabled, the global log store, its settings and the global macros are removed,
+/// and `Logger` with the `local_log!` macro is the whole logging API.
+/// None of the global settings, such as the global minimum level, silenced channels or the clock, apply to it.
+/// The listener is called while the logger is locked, so it must not log to the same logger.
+/// Note that the `local_log!` macro is the preferred method to log to a `Logger` in the public API.
+/// ```rust
+/// use breadcrumbs::{local_log, Logger, LogLevel};
+/// let motor = Logger::new();
+/// let sensor = Logger::new();
+/// local_log!(&motor, LogLevel::Error, "motor", "Stalled");
+/// assert_eq!(motor.len(), 1);
+/// assert!(sensor.is_empty());
+/// ```
+pub struct Logger {
+    state: Mutex<LoggerState>,
+}
+
+struct LoggerState {
+    logs: Vec<Log>,
+    listener: Option<Box<dyn LogListener>>,
+    /// Set while `listener` is taken out to run one of its hooks with the logger unlocked.
+    listener_busy: bool,
+    /// Logs made while the listener is busy, passed to it once its hook returns.
+    missed: Vec<Log>,
+    /// Incremented whenever the listener is replaced or removed, so a busy listener is not put back over its replacement.
+    listener_generation: u64,
+    capacity: Option<usize>,
+    min_level: LogLevel,
+    clock: Option<fn() -> u64>,
+    next_sequence: u64,
+}
+
+impl Default for Logger {
+    fn default() -> Logger {
+        Logger::new()
+    }
+}
+
+impl Logger {
+    /// Creates an unbounded logger without a listener.
+    /// ```rust
+    /// use breadcrumbs::Logger;
+    /// let logger = Logger::new();
+    /// assert!(logger.is_empty());
+    /// ```
+    pub const fn new() -> Logger {
+        Logger {
+            state: Mutex::new(LoggerState {
+                logs: Vec::new(),
+                listener: None,
+                listener_busy: false,
+                missed: Vec::new(),
+                listener_generation: 0,
+                capacity: None,
+                min_level: LogLevel::Verbose,
+                clock: None,
+                next_sequence: 1,
+            }),
+        }
+    }
+
+    /// Creates an unbounded logger that passes every log to the given listener.
+    /// ```rust
+    /// use breadcrumbs::{Log, Logger};
+    /// let logger = Logger::with_listener(Box::new(|log: Log| println!("{}", log)));
+    /// ```
+    pub fn with_listener(listener: Box<dyn LogListener>) -> Logger {
+        let logger = Logger::new();
+        logger.set_listener(listener);
+        logger
+    }
+
+    /// Creates a logger that stores at most `capacity` logs, evicting the oldest log when full.
+    /// ```rust
+    /// use breadcrumbs::Logger;
+    /// let logger = Logger::with_capacity(16);
+    /// assert_eq!(logger.capacity(), Some(16));
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Logger {
+        let logger = Logger::new();
+        logger.set_capacity(capacity);
+        logger
+    }
+
+    /// Replaces the logger's listener.
+    /// ```rust
+    /// use breadcrumbs::{Log, Logger};
+    /// let logger = Logger::new();
+    /// logger.set_listener(Box::new(|log: Log| println!("{}", log)));
+    /// ```
+    pub fn set_listener(&self, listener: Box<dyn LogListener>) {
+        let mut state = self.state.lock();
+        Logger::replace_listener(&mut state, Some(listener));
+        self.with_listener_released(state, |listener| listener.on_init());
+    }
+
+    /// Removes the logger's listener.
+    /// ```rust
+    /// use breadcrumbs::Logger;
+    /// let logger = Logger::new();
+    /// logger.clear_listener();
+    /// ```
+    pub fn clear_listener(&self) {
+        Logger::replace_listener(&mut self.state.lock(), None);
+    }
+
+    fn replace_listener(state: &mut LoggerState, listener: Option<Box<dyn LogListener>>) {
+        state.listener = listener;
+        state.listener_busy = false;
+        state.missed.clear();
+        state.listener_generation += 1;
+    }
+
+    /// Takes the listener out, unlocks the logger and runs `f` on it, so the listener can log to this logger from its hooks.
+    /// Logs made in the meantime are passed to the listener once `f` returns, unless it was replaced or removed.
+    /// Does nothing if there is no listener or it is already busy.
+    fn with_listener_released(&self, mut state: spin::MutexGuard<'_, LoggerState>, f: impl FnOnce(&mut Box<dyn LogListener>)) {
+        let Some(mut listener) = state.listener.take() else {
+            return;
+        };
+        state.listener_busy = true;
+        let generation = state.listener_generation;
+        drop(state);
+        f(&mut listener);
+        loop {
+            let mut state = self.state.lock();
+            if state.listener_generation != generation {
+                return;
+            }
+            if state.missed.is_empty() {
+                state.listener = Some(listener);
+                state.listener_busy = false;
+                return;
+            }
+            let missed = core::mem::take(&mut state.missed);
+            drop(state);
+            for log in missed {
+                if listener.filter_hook(&log) {
+                    listener.on_log(log);
+                }
+            }
+        }
+    }
+
+    /// Returns the logger's capacity, or `None` if it is unbounded.
+    /// ```rust
+    /// use breadcrumbs::Logger;
+    /// assert_eq!(Logger::new().capacity(), None);
+    /// ```
+    pub fn capacity(&self) -> Option<usize> {
+        self.state.lock().capacity
+    }
+
+    /// Changes the logger's capacity, evicting the oldest logs straight away if it holds more than `capacity`.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger};
+    /// let logger = Logger::new();
+    /// local_log!(&logger, "First");
+    /// local_log!(&logger, "Second");
+    /// logger.set_capacity(1);
+    /// assert_eq!(logger.traceback(None, None).0[0].message, "Second");
+    /// ```
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut state = self.state.lock();
+        state.capacity = Some(capacity);
+        let len = state.logs.len();
+        if len > capacity {
+            state.logs.drain(..len - capacity);
+        }
+    }
+
+    /// Sets the minimum log level the logger stores; less severe logs are discarded.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger, LogLevel};
+    /// let logger = Logger::new();
+    /// logger.set_min_level(LogLevel::Warn);
+    /// local_log!(&logger, LogLevel::Info, "net", "Connected");
+    /// assert!(logger.is_empty());
+    /// ```
+    pub fn set_min_level(&self, level: LogLevel) {
+        self.state.lock().min_level = level;
+    }
+
+    /// Returns the minimum log level the logger stores.
+    /// ```rust
+    /// use breadcrumbs::{Logger, LogLevel};
+    /// assert_eq!(Logger::new().min_level(), LogLevel::Verbose);
+    /// ```
+    pub fn min_level(&self) -> LogLevel {
+        self.state.lock().min_level
+    }
+
+    /// Returns whether a log of the given level would be stored.
+    /// ```rust
+    /// use breadcrumbs::{Logger, LogLevel};
+    /// let logger = Logger::new();
+    /// logger.set_min_level(LogLevel::Warn);
+    /// assert!(logger.level_enabled(LogLevel::Error));
+    /// assert!(!logger.level_enabled(LogLevel::Info));
+    /// ```
+    pub fn level_enabled(&self, level: LogLevel) -> bool {
+        level.is_at_least(self.min_level())
+    }
+
+    /// Registers a function that the logger calls to timestamp each log.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger};
+    /// fn ticks() -> u64 {
+    ///     42
+    /// }
+    ///
+    /// let logger = Logger::new();
+    /// logger.set_clock(ticks);
+    /// local_log!(&logger, "Test log message");
+    /// assert_eq!(logger.last_log(None).unwrap().timestamp, Some(42));
+    /// ```
+    pub fn set_clock(&self, clock: fn() -> u64) {
+        self.state.lock().clock = Some(clock);
+    }
+
+    /// Unregisters the logger's clock, so new logs are no longer timestamped.
+    /// ```rust
+    /// use breadcrumbs::Logger;
+    /// let logger = Logger::new();
+    /// logger.clear_clock();
+    /// ```
+    pub fn clear_clock(&self) {
+        self.state.lock().clock = None;
+    }
+
+    /// Logs a message with an optional log level and channel to this logger.
+    /// Note that the `local_log!` macro is the preferred method to do this in the public API.
+    /// ```rust
+    /// use breadcrumbs::{Logger, LogLevel};
+    /// let logger = Logger::new();
+    /// logger.log(Some(LogLevel::Info), Some(String::from("test_channel")), String::from("Test log message"));
+    /// logger.log(None, None, String::from("Test log message without a channel"));
+    /// assert_eq!(logger.len(), 2);
+    /// ```
+    pub fn log(&self, level: Option<LogLevel>, channel: Option<String>, message: String) {
+        self.store(Log::new(channel.unwrap_or_default(), level.unwrap_or(LogLevel::Info), message));
+    }
+
+    /// Logs a message to this logger along with the source-code location it originated from.
+    /// This is used by the `local_log!` macro and is not part of the public API.
+    #[doc(hidden)]
+    pub fn _log_with_location(&self, level: Option<LogLevel>, channel: Option<&str>, message: String, file: &'static str, line: u32, column: u32) {
+        self.store(Log::with_location(String::from(channel.unwrap_or(NO_CHANNEL)), level.unwrap_or(LogLevel::Info), message, file, line, column));
+    }
+
+    fn store(&self, mut log: Log) {
+        let mut state = self.state.lock();
+        if !log.level.is_at_least(state.min_level) {
+            return;
+        }
+        log.sequence = state.next_sequence;
+        state.next_sequence += 1;
+        log.timestamp = state.clock.map(|clock| clock());
+        match state.capacity {
+            Some(0) => {}
+            Some(capacity) if state.logs.len() >= capacity => {
+                state.logs.remove(0);
+                state.logs.push(log.clone());
+            }
+            _ => state.logs.push(log.clone()),
+        }
+        if state.listener_busy {
+            state.missed.push(log);
+            return;
+        }
+        self.with_listener_released(state, |listener| {
+            if listener.filter_hook(&log) {
+                listener.on_log(log);
+            }
+        });
+    }
+
+    /// Returns the number of logs currently stored.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger};
+    /// let logger = Logger::new();
+    /// local_log!(&logger, "Test log message");
+    /// assert_eq!(logger.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.state.lock().logs.len()
+    }
+
+    /// Returns whether the logger holds no logs.
+    /// ```rust
+    /// use breadcrumbs::Logger;
+    /// assert!(Logger::new().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all logs from the logger, then calls the listener's `on_flush`.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger};
+    /// let logger = Logger::new();
+    /// local_log!(&logger, "Test log message");
+    /// logger.clear_logs();
+    /// assert!(logger.is_empty());
+    /// ```
+    pub fn clear_logs(&self) {
+        let mut state = self.state.lock();
+        state.logs.clear();
+        self.with_listener_released(state, |listener| listener.on_flush());
+    }
+
+    /// Generates a `Traceback` of the stored logs, optionally filtered by minimum log level and channels.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger, LogLevel};
+    /// let logger = Logger::new();
+    /// local_log!(&logger, LogLevel::Error, "motor", "Stalled");
+    /// local_log!(&logger, LogLevel::Info, "net", "Connected");
+    /// assert_eq!(logger.traceback(None, None).len(), 2);
+    /// assert_eq!(logger.traceback(LogLevel::Warn, None).len(), 1);
+    /// assert_eq!(logger.traceback(None, Some(vec![String::from("net")])).len(), 1);
+    /// ```
+    pub fn traceback(&self, min_level: impl Into<LevelFilter>, channels: Option<Vec<String>>) -> Traceback {
+        let min_level = min_level.into();
+        let channels: Option<Vec<&str>> = channels.as_ref().map(|channels| channels.iter().map(String::as_str).collect());
+        Traceback(
+            self.state.lock().logs
+                .iter()
+                .filter(|log| log.matches_filter(min_level, channels.as_deref()))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Removes and returns the stored logs matching the minimum log level and channels, leaving the rest in place.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger, LogLevel};
+    /// let logger = Logger::new();
+    /// local_log!(&logger, LogLevel::Error, "motor", "Stalled");
+    /// local_log!(&logger, LogLevel::Info, "net", "Connected");
+    /// assert_eq!(logger.drain_logs(LogLevel::Warn, None).len(), 1);
+    /// assert_eq!(logger.len(), 1);
+    /// ```
+    pub fn drain_logs(&self, min_level: impl Into<LevelFilter>, channels: Option<Vec<String>>) -> Traceback {
+        let min_level = min_level.into();
+        let channels: Option<Vec<&str>> = channels.as_ref().map(|channels| channels.iter().map(String::as_str).collect());
+        let mut state = self.state.lock();
+        let (drained, kept) = core::mem::take(&mut state.logs)
+            .into_iter()
+            .partition(|log| log.matches_filter(min_level, channels.as_deref()));
+        state.logs = kept;
+        Traceback(drained)
+    }
+
+    /// Retrieves the most recent stored log that is at least as severe as the minimum log level.
+    /// ```rust
+    /// use breadcrumbs::{local_log, Logger, LogLevel};
+    /// let logger = Logger::new();
+    /// local_log!(&logger, LogLevel::Error, "motor", "Stalled");
+    /// local_log!(&logger, LogLevel::Info, "motor", "Retrying");
+    /// assert_eq!(logger.last_log(None).unwrap().message, "Retrying");
+    /// assert_eq!(logger.last_log(LogLevel::Warn).unwrap().message, "Stalled");
+    /// ```
+    pub fn last_log(&self, min_level: impl Into<LevelFilter>) -> Option<Log> {
+        let min_level = min_level.into();
+        self.state.lock().logs
+            .iter()
+            .rev()
+            .find(|log| log.matches_filter(min_level, None))
+            .cloned()
+    }
+}
+
+/// A macro for generating a `Traceback` of logs, optionally filtered by log level and channel.
+/// 
+/// To only specify a `LogLevel`, use the `traceback_level!` macro.
+/// 
+/// To only specify a `channel`, use the `traceback_channel!` macro.
+/// 
+/// To filter by several channels at once, pass them as an array.
+/// 
+/// # Examples
+/// 
+/// Traceback with default values:
+/// 
+/// ```
+/// use breadcrumbs::traceback;
+/// let traceback = traceback!();
+/// ```
+/// 
+/// Traceback with a custom log level and channel:
+/// 
+/// ```
+/// use breadcrumbs::{traceback, LogLevel};
+/// let traceback = traceback!(LogLevel::Warn, "test_channel");
+/// ```
+/// 
+/// Traceback with a custom log level and several channels:
+/// 
+/// ```
+/// use breadcrumbs::{traceback, LogLevel};
+/// let traceback = traceback!(LogLevel::Warn, ["sensor", "actuator"]);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback {
+    () => {
+        $crate::get_logs_traceback(None, None, None, None)
+    };
+    ($arg1:expr, [$($channel:expr),* $(,)?]) => {
+        $crate::traceback_channels!($arg1, [$($channel),*])
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::get_logs_traceback(Some($arg1), Some(vec![$arg2.to_string()]), None, None)
+    };
+}
+
+/// A macro for generating a `Traceback` of logs given a log level and several channels.
+/// 
+/// # Examples
+/// 
+/// Basic usage:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_channels, LogLevel};
+/// let traceback = traceback_channels!(LogLevel::Warn, ["sensor", "actuator"]);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_channels {
+    ($arg1:expr, [$($channel:expr),* $(,)?]) => {
+        $crate::get_logs_traceback_multi(Some($arg1), &[$($channel),*])
+    };
+}
+
+/// A macro for generating a `Traceback` of the last `n` logs, optionally filtered by log level and channel.
+/// 
+/// # Examples
+/// 
+/// The last 20 logs:
+/// 
+/// ```
+/// use breadcrumbs::traceback_tail;
+/// let traceback = traceback_tail!(20);
+/// ```
+/// 
+/// The last 10 logs of level `Error` or higher on the channel `net`:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_tail, LogLevel};
+/// let traceback = traceback_tail!(LogLevel::Error, "net", 10);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_tail {
+    ($arg1:expr) => {
+        $crate::traceback!().tail($arg1)
+    };
+    ($arg1:expr, $arg2:expr, $arg3:expr) => {
+        $crate::traceback!($arg1, $arg2).tail($arg3)
+    };
+}
+
+/// A macro for generating a `Traceback` of the first `n` logs, optionally filtered by log level and channel.
+/// 
+/// # Examples
+/// 
+/// The first 20 logs:
+/// 
+/// ```
+/// use breadcrumbs::traceback_head;
+/// let traceback = traceback_head!(20);
+/// ```
+/// 
+/// The first 10 logs of level `Error` or higher on the channel `net`:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_head, LogLevel};
+/// let traceback = traceback_head!(LogLevel::Error, "net", 10);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_head {
+    ($arg1:expr) => {
+        $crate::traceback!().head($arg1)
+    };
+    ($arg1:expr, $arg2:expr, $arg3:expr) => {
+        $crate::traceback!($arg1, $arg2).head($arg3)
+    };
+}
+
+/// A macro for generating a `Traceback` of logs whose level is within a range, optionally filtered by channel.
+/// 
+/// # Examples
+/// 
+/// Only `Warn` and `Error` logs:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_range, LogLevel};
+/// let traceback = traceback_range!(LogLevel::Warn, LogLevel::Error);
+/// ```
+/// 
+/// Only `Warn` and `Error` logs on a channel:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_range, LogLevel};
+/// let traceback = traceback_range!(LogLevel::Warn, LogLevel::Error, "test_channel");
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_range {
+    ($arg1:expr, $arg2:expr) => {
+        $crate::get_logs_traceback_range($arg1, $arg2, None)
+    };
+    ($arg1:expr, $arg2:expr, $arg3:expr) => {
+        $crate::get_logs_traceback_range($arg1, $arg2, Some(vec![$arg3.to_string()]))
+    };
+}
+
+/// A macro for generating a `Traceback` of logs whose sequence number is between `start` and `end` (inclusive),
+/// optionally filtered by log level and channel.
+/// 
+/// # Examples
+/// 
+/// Every log in a sequence window:
+/// 
+/// ```
+/// use breadcrumbs::traceback_between;
+/// let traceback = traceback_between!(10, 20);
+/// ```
+/// 
+/// Only logs of level `Warn` or higher on a channel:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_between, LogLevel};
+/// let traceback = traceback_between!(10, 20, LogLevel::Warn, "net");
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_between {
+    ($arg1:expr, $arg2:expr) => {
+        $crate::get_logs_traceback_between($arg1, $arg2, None, None)
+    };
+    ($arg1:expr, $arg2:expr, $arg3:expr, $arg4:expr) => {
+        $crate::get_logs_traceback_between($arg1, $arg2, Some($arg3), Some(vec![$arg4.to_string()]))
+    };
+}
+
+/// A macro for generating a `Traceback` of logs given only a log level.
+/// 
+/// # Examples
+/// 
+/// Basic usage:
+/// 
+/// ```
+/// use breadcrumbs::{traceback_level, LogLevel};
+/// let traceback = traceback_level!(LogLevel::Warn);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_level {
+    ($arg1:expr) => {
+        $crate::get_logs_traceback(Some($arg1), None, None, None)
+    };
+}
+
+/// A macro for generating a `Traceback` of at most `n` logs, keeping the oldest ones.
+/// This bounds the memory used by the traceback no matter how many logs are stored.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use breadcrumbs::{log, traceback_max};
+/// log!("First");
+/// log!("Second");
+/// let traceback = traceback_max!(1);
+/// assert_eq!(traceback.len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_max {
+    ($arg1:expr) => {
+        $crate::get_logs_traceback(None, None, None, Some($arg1))
+    };
+}
+
+/// A macro for generating a `Traceback` of logs given a log level, leaving out one or several channels.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use breadcrumbs::{traceback_excluding, LogLevel};
+/// let traceback = traceback_excluding!(LogLevel::Info, "noisy_channel");
+/// let traceback = traceback_excluding!(LogLevel::Info, ["noisy_channel", "telemetry"]);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_excluding {
+    ($arg1:expr, [$($channel:expr),* $(,)?]) => {
+        $crate::get_logs_traceback_excluding(Some($arg1), Some(vec![$($channel.to_string()),*]))
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::get_logs_traceback_excluding(Some($arg1), Some(vec![$arg2.to_string()]))
+    };
+}
+
+/// A macro for generating a `Traceback` of logs given only a channel.
+/// 
+/// # Examples
+/// 
+/// Basic usage:
+/// 
+/// ```
+/// use breadcrumbs::traceback_channel;
+/// let traceback = traceback_channel!("test_channel");
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! traceback_channel {
+    ($arg1:expr) => {
+        $crate::get_logs_traceback(None, Some(vec![$arg1.to_string()]), None, None)
+    };
+}
+
+/// A macro for retrieving the most recent log, optionally of at least the given log level.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use breadcrumbs::{last_log, log, LogLevel};
+/// log!(LogLevel::Warn, "motor", "Overheating");
+/// assert_eq!(last_log!().unwrap().message, "Overheating");
+/// assert!(last_log!(LogLevel::Error).is_none());
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! last_log {
+    () => {
+        $crate::get_last_log(None)
+    };
+    ($arg1:expr) => {
+        $crate::get_last_log(Some($arg1))
+    };
+}
+
+/// A macro for retrieving the most recent log in a channel, optionally of at least the given log level.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use breadcrumbs::{last_log_channel, log, LogLevel};
+/// log!(LogLevel::Warn, "motor", "Overheating");
+/// assert_eq!(last_log_channel!("motor").unwrap().message, "Overheating");
+/// assert!(last_log_channel!("motor", LogLevel::Error).is_none());
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! last_log_channel {
+    ($arg1:expr) => {
+        $crate::get_last_log_for_channel($arg1, None)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::get_last_log_for_channel($arg1, Some($arg2))
+    };
+}
+
+
+
+/// A macro for logging messages with an optional log level and channel.
+/// 
+/// To only specify a `LogLevel`, use the `log_level!` macro.
+/// 
+/// To only specify a `channel`, use the `log_channel!` macro.
+/// 
+/// # Examples
+/// 
+/// Log with a log level, channel and message
+/// ```rust
+/// use breadcrumbs::{log, LogLevel};
+/// log!(LogLevel::Info, "test_channel", "Test log message");
+/// ```
+/// 
+/// Log with just a message
+/// 
+/// ```rust
+/// use breadcrumbs::log;
+/// log!("Test log message");
+/// ```
+/// 
+/// Log with a formatted message, with or without a log level and channel
+/// 
+/// ```rust
+/// use breadcrumbs::{log, LogLevel};
+/// let x = 42;
+/// log!("value is {}", x);
+/// log!(LogLevel::Info, "test_channel", "value is {}", x);
+/// ```
+/// 
+/// Log to several channels at once by passing them as an array
+/// 
+/// ```rust
+/// use breadcrumbs::{log, traceback_channel, LogLevel};
+/// log!(LogLevel::Warn, ["network", "security"], "Port scan detected");
+/// assert_eq!(traceback_channel!("security").len(), 1);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log {
+    ($fmt:literal, $($arg:expr),+ $(,)?) => {
+        if $crate::_level_enabled($crate::LogLevel::Info) {
+            extern crate alloc;
+            $crate::_log_with_location(None, None, alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    };
+    ($arg1:expr, [$($channel:expr),+ $(,)?], $fmt:literal, $($arg:expr),+ $(,)?) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            extern crate alloc;
+            $crate::_log_multi_with_location(level, &[$($crate::_ChannelArg::_channel_name(&$channel)),+], alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    }};
+    ($arg1:expr, [$($channel:expr),+ $(,)?], $arg3:expr) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            $crate::_log_multi_with_location(level, &[$($crate::_ChannelArg::_channel_name(&$channel)),+], $arg3.to_string(), file!(), line!(), column!())
+        }
+    }};
+    ($arg1:expr, $arg2:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            extern crate alloc;
+            $crate::_log_with_location(Some(level), Some($crate::_ChannelArg::_channel_name(&$arg2)), alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    }};
+    ($arg1:expr, $arg2:expr, $arg3:expr) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            $crate::_log_with_location(Some(level), Some($crate::_ChannelArg::_channel_name(&$arg2)), $arg3.to_string(), file!(), line!(), column!())
+        }
+    }};
+    ($arg1:expr) => {
+        if $crate::_level_enabled($crate::LogLevel::Info) {
+            $crate::_log_with_location(None, None, $arg1.to_string(), file!(), line!(), column!())
+        }
+    };
+}
+
+/// A macro for logging messages with a log level only.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_level, LogLevel};
+/// log_level!(LogLevel::Info, "Test log message");
+/// ```
+/// 
+/// Log with a formatted message
+/// 
+/// ```rust
+/// use breadcrumbs::{log_level, LogLevel};
+/// log_level!(LogLevel::Info, "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_level {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            extern crate alloc;
+            $crate::_log_with_location(Some(level), None, alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    }};
+    ($arg1:expr, $arg2:expr) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            $crate::_log_with_location(Some(level), None, $arg2.to_string(), file!(), line!(), column!())
+        }
+    }};
+}
+
+/// A macro for logging messages with a channel only.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_channel, LogLevel};
+/// log_channel!("test_channel", "Test log message");
+/// ```
+/// 
+/// Log with a formatted message
+/// 
+/// ```rust
+/// use breadcrumbs::log_channel;
+/// log_channel!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_channel {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        if $crate::_level_enabled($crate::LogLevel::Info) {
+            extern crate alloc;
+            $crate::_log_with_location(None, Some($crate::_ChannelArg::_channel_name(&$arg1)), alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    };
+    ($arg1:expr, $arg2:expr) => {
+        if $crate::_level_enabled($crate::LogLevel::Info) {
+            $crate::_log_with_location(None, Some($crate::_ChannelArg::_channel_name(&$arg1)), $arg2.to_string(), file!(), line!(), column!())
+        }
+    };
+}
+
+/// A macro for logging messages with a log level, channel and structured key-value metadata.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_meta, LogLevel};
+/// log_meta!(LogLevel::Error, "net", "connect failed", { host => "192.168.1.1", port => "443" });
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_meta {
+    ($arg1:expr, $arg2:expr, $arg3:expr, { $($key:ident => $value:expr),* $(,)? }) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) {
+            $crate::_log_with_meta(level, $crate::_ChannelArg::_channel_name(&$arg2), $arg3.to_string(), &[$((stringify!($key), $value.to_string().as_str())),*], file!(), line!(), column!())
+        }
+    }};
+}
+
+/// A macro for logging a verbose-level message to a channel, without needing to import `LogLevel`.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::log_verbose;
+/// log_verbose!("test_channel", "Test log message");
+/// log_verbose!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_verbose {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        $crate::log!($crate::LogLevel::Verbose, $arg1, $fmt, $($arg),+)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::log!($crate::LogLevel::Verbose, $arg1, $arg2)
+    };
+}
+
+/// A macro for logging a debug-level message to a channel, without needing to import `LogLevel`.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::log_debug;
+/// log_debug!("test_channel", "Test log message");
+/// log_debug!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_debug {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        $crate::log!($crate::LogLevel::Debug, $arg1, $fmt, $($arg),+)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::log!($crate::LogLevel::Debug, $arg1, $arg2)
+    };
+}
+
+/// A macro for logging an info-level message to a channel, without needing to import `LogLevel`.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::log_info;
+/// log_info!("test_channel", "Test log message");
+/// log_info!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_info {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        $crate::log!($crate::LogLevel::Info, $arg1, $fmt, $($arg),+)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::log!($crate::LogLevel::Info, $arg1, $arg2)
+    };
+}
+
+/// A macro for logging a warning-level message to a channel, without needing to import `LogLevel`.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::log_warn;
+/// log_warn!("test_channel", "Test log message");
+/// log_warn!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_warn {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        $crate::log!($crate::LogLevel::Warn, $arg1, $fmt, $($arg),+)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::log!($crate::LogLevel::Warn, $arg1, $arg2)
+    };
+}
+
+/// A macro for logging an error-level message to a channel, without needing to import `LogLevel`.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::log_error;
+/// log_error!("test_channel", "Test log message");
+/// log_error!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_error {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        $crate::log!($crate::LogLevel::Error, $arg1, $fmt, $($arg),+)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::log!($crate::LogLevel::Error, $arg1, $arg2)
+    };
+}
+
+/// A macro for logging a critical-level message to a channel, without needing to import `LogLevel`.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::log_critical;
+/// log_critical!("test_channel", "Test log message");
+/// log_critical!("test_channel", "value is {}", 42);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_critical {
+    ($arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        $crate::log!($crate::LogLevel::Critical, $arg1, $fmt, $($arg),+)
+    };
+    ($arg1:expr, $arg2:expr) => {
+        $crate::log!($crate::LogLevel::Critical, $arg1, $arg2)
+    };
+}
+
+/// A macro for logging a message with a log level and channel only when a condition is true.
+/// The message is not evaluated or formatted when the condition is false, which keeps tight loops free of allocations.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_if, LogLevel};
+/// let temperature = 95;
+/// log_if!(temperature > 90, LogLevel::Error, "thermal", "Overheating");
+/// log_if!(temperature > 90, LogLevel::Error, "thermal", "Temperature is {}", temperature);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_if {
+    ($cond:expr, $arg1:expr, $arg2:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        if $cond {
+            $crate::log!($arg1, $arg2, $fmt, $($arg),+)
+        }
+    };
+    ($cond:expr, $arg1:expr, $arg2:expr, $arg3:expr) => {
+        if $cond {
+            $crate::log!($arg1, $arg2, $arg3)
+        }
+    };
+}
+
+/// A macro for creating a `LogWriter` with a log level and channel, which collects `write!` output into one log.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_write, last_log, LogLevel};
+/// use core::fmt::Write;
+/// let errno = 104;
+/// write!(log_write!(LogLevel::Error, "net"), "errno={}", errno).unwrap();
+/// assert_eq!(last_log!().unwrap().message, "errno=104");
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_write {
+    ($arg1:expr, $arg2:expr) => {
+        $crate::LogWriter::new($arg1, $crate::_ChannelArg::_channel_name(&$arg2))
+    };
+}
+
+/// A macro for logging the entry and exit of the current scope at `LogLevel::Verbose`,
+/// by binding a `ScopedLog` guard that lives until the end of the enclosing block.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{scope_log, traceback_channel};
+/// fn calibrate() {
+///     scope_log!("motor", "calibrate");
+///     // ...
+/// }
+/// 
+/// calibrate();
+/// assert_eq!(traceback_channel!("motor").len(), 2);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! scope_log {
+    ($arg1:expr, $arg2:expr) => {
+        let _guard = $crate::ScopedLog::enter($crate::_ChannelArg::_channel_name(&$arg1), $arg2);
+    };
+}
+
+/// A macro for logging a message built by a closure, which is only called if the log level is enabled and the channel is not silenced.
+/// Use it when building the message is expensive, e.g. for verbose dumps of device state.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_lazy, LogLevel};
+/// let registers = [0x12u8, 0x34, 0x56];
+/// log_lazy!(LogLevel::Verbose, "i2c", || format!("registers: {:02x?}", registers));
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_lazy {
+    ($arg1:expr, $arg2:expr, $arg3:expr) => {
+        $crate::_log_lazy($arg1, $crate::_ChannelArg::_channel_name(&$arg2), $arg3, file!(), line!(), column!())
+    };
+}
+
+/// A macro for timing a block, logging `"→ name"` at `LogLevel::Verbose` before it runs and `"← name (n ticks)"` after it,
+/// using the clock registered by `set_clock`. Without a clock the tick count is left out.
+/// The macro evaluates to the value of the block.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_measure, set_clock, clear_clock, traceback_channel};
+/// fn ticks() -> u64 {
+///     42
+/// }
+/// 
+/// set_clock(ticks);
+/// let sum = log_measure!("perf", "sum", { (1..=10).sum::<u32>() });
+/// assert_eq!(sum, 55);
+/// assert_eq!(traceback_channel!("perf").0[1].message, "← sum (0 ticks)");
+/// clear_clock();
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_measure {
+    ($arg1:expr, $arg2:expr, $arg3:block) => {{
+        let channel = $crate::_ChannelArg::_channel_name(&$arg1);
+        let __label = $arg2;
+        let start = $crate::_measure_start(channel, __label, file!(), line!(), column!());
+        let result = $arg3;
+        $crate::_measure_end(channel, __label, start, file!(), line!(), column!());
+        result
+    }};
+}
+
+/// A macro for asserting that a condition is true, logging a message at `LogLevel::Critical` before panicking if it is not.
+/// Unlike `assert!`, the failure is guaranteed to be stored and passed to every listener before unwinding begins, even if
+/// the channel is silenced, sampled or rate limited. A handler registered with `set_assert_handler` is called instead of panicking.
+/// 
+/// # Examples
+/// 
+/// ```rust,should_panic
+/// use breadcrumbs::log_assert;
+/// let voltage = 2;
+/// log_assert!(voltage > 3, "power", "invariant violated: voltage is {}", voltage);
+/// ```
+/// 
+/// Assert without a message
+/// 
+/// ```rust
+/// use breadcrumbs::log_assert;
+/// log_assert!(1 + 1 == 2);
+/// log_assert!(true, "power", "Never logged");
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_assert {
+    ($cond:expr, $arg1:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {
+        if !$cond {
+            extern crate alloc;
+            $crate::_assert_failed($crate::_ChannelArg::_channel_name(&$arg1), alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    };
+    ($cond:expr, $arg1:expr, $arg2:expr) => {
+        if !$cond {
+            $crate::_assert_failed($crate::_ChannelArg::_channel_name(&$arg1), $arg2.to_string(), file!(), line!(), column!())
+        }
+    };
+    ($cond:expr) => {
+        if !$cond {
+            extern crate alloc;
+            $crate::_assert_failed("", alloc::format!("assertion failed: {}", stringify!($cond)), file!(), line!(), column!())
+        }
+    };
+}
+
+/// A macro for logging a message at most once per call site, no matter how many times it is reached.
+/// Useful in interrupt handlers and tight loops. A call site that is reached while its level is disabled or its channel is silenced,
+/// or whose log is dropped by sampling or rate limiting, does not count.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{log_once, traceback_channel, LogLevel};
+/// for _ in 0..10 {
+///     log_once!(LogLevel::Error, "hw", "DMA stall detected");
+/// }
+/// assert_eq!(traceback_channel!("hw").len(), 1);
+/// ```
+/// 
+/// Log with a formatted message
+/// 
+/// ```rust
+/// use breadcrumbs::{log_once, LogLevel};
+/// let address = 0x4000;
+/// log_once!(LogLevel::Warn, "hw", "Unaligned access at {:#x}", address);
+/// ```
+#[cfg(not(feature = "no_global"))]
+#[macro_export]
+macro_rules! log_once {
+    ($arg1:expr, $arg2:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) && !$crate::_logged_once_at(file!(), line!()) {
+            extern crate alloc;
+            $crate::_log_once_with_location(level, $crate::_ChannelArg::_channel_name(&$arg2), alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    }};
+    ($arg1:expr, $arg2:expr, $arg3:expr) => {{
+        let level = $arg1;
+        if $crate::_level_enabled(level) && !$crate::_logged_once_at(file!(), line!()) {
+            $crate::_log_once_with_location(level, $crate::_ChannelArg::_channel_name(&$arg2), $arg3.to_string(), file!(), line!(), column!())
+        }
+    }};
+}
+
+/// A macro for logging messages to a `Logger` instead of the global log store,
+/// taking the logger followed by the same arguments as the `log!` macro.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use breadcrumbs::{local_log, Logger, LogLevel};
+/// let logger = Logger::new();
+/// local_log!(&logger, LogLevel::Info, "test_channel", "Test log message");
+/// local_log!(&logger, "Test log message");
+/// ```
+/// 
+/// Log with a formatted message
+/// 
+/// ```rust
+/// use breadcrumbs::{local_log, Logger, LogLevel};
+/// let logger = Logger::new();
+/// let x = 42;
+/// local_log!(&logger, "value is {}", x);
+/// local_log!(&logger, LogLevel::Info, "test_channel", "value is {}", x);
+/// ```
+#[macro_export]
+macro_rules! local_log {
+    ($logger:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {{
+        let logger: &$crate::Logger = $logger;
+        if logger.level_enabled($crate::LogLevel::Info) {
+            extern crate alloc;
+            logger._log_with_location(None, None, alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    }};
+    ($logger:expr, $arg1:expr, $arg2:expr, $fmt:literal, $($arg:expr),+ $(,)?) => {{
+        let logger: &$crate::Logger = $logger;
+        let level = $arg1;
+        if logger.level_enabled(level) {
+            extern crate alloc;
+            logger._log_with_location(Some(level), Some($crate::_ChannelArg::_channel_name(&$arg2)), alloc::format!($fmt, $($arg),+), file!(), line!(), column!())
+        }
+    }};
+    ($logger:expr, $arg1:expr, $arg2:expr, $arg3:expr) => {{
+        let logger: &$crate::Logger = $logger;
+        let level = $arg1;
+        if logger.level_enabled(level) {
+            logger._log_with_location(Some(level), Some($crate::_ChannelArg::_channel_name(&$arg2)), $arg3.to_string(), file!(), line!(), column!())
+        }
+    }};
+    ($logger:expr, $arg1:expr) => {{
+        let logger: &$crate::Logger = $logger;
+        if logger.level_enabled($crate::LogLevel::Info) {
+            logger._log_with_location(None, None, $arg1.to_string(), file!(), line!(), column!())
+        }
+    }};
+}
+
+
+
+#[cfg(all(test, not(feature = "no_global")))]
+mod tests {
+    extern crate std;
+    use super::*;
+    use alloc::vec;
+    use crate::alloc::string::ToString;
+
+    // The logging system is global, so tests that depend on its state must not run concurrently
+    static TEST_LOCK: Mutex<()> = Mutex::new(());
+    
+    // Test Log and LogListener
+    struct MockLogListener {
+        received_log: Option<Log>,
+    }
+
+    impl MockLogListener {
+        fn new() -> Self {
+            MockLogListener { received_log: None }
+        }
+    }
+
+    impl LogListener for MockLogListener {
+        fn on_log(&mut self, log: Log) {
+            self.received_log = Some(log);
+        }
+    }
+
+    // Wrapper struct that implements LogListener for Arc<Mutex<MockLogListener>>
+    struct MockLogListenerWrapper(Arc<Mutex<MockLogListener>>);
+
+    impl LogListener for MockLogListenerWrapper {
+        fn on_log(&mut self, log: Log) {
+            self.0.lock().on_log(log);
+        }
+    }
+
+    #[test]
+    fn test_log_creation_and_handling() {
+        let _guard = TEST_LOCK.lock();
+        let mock_listener = Arc::new(Mutex::new(MockLogListener::new()));
+        let mock_listener_wrapper = MockLogListenerWrapper(mock_listener.clone());
+        init!(mock_listener_wrapper);
+
+        log!(LogLevel::Info, "test_channel", "Test log message");
+
+        let received_log = mock_listener.lock().received_log.clone().expect("Log not received by listener");
+        assert_eq!(received_log.level, LogLevel::Info);
+        assert_eq!(received_log.channel, "test_channel");
+        assert_eq!(received_log.message, "Test log message");
+    }
+
+    // Test traceback generation
+    #[test]
+    fn test_traceback_generation() {
+        let _guard = TEST_LOCK.lock();
+        log!(LogLevel::Info, "channel1", "Log 1");
+        log!(LogLevel::Warn, "channel2", "Log 2");
+        log!(LogLevel::Error, "channel1", "Log 3");
+
+        let traceback = traceback!(LogLevel::Warn, "channel2").to_string();
+        assert!(traceback.contains("[channel2/Warn    ] Log 2"));
+        assert!(!traceback.contains("[channel1/Info    ] Log 1"));
+        assert!(!traceback.contains("[channel1/Error   ] Log 3"));
+    }
+
+    // Test log macros
+    #[test]
+    fn test_log_macros() {
+        let _guard = TEST_LOCK.lock();
+        log!(LogLevel::Info, "test_channel", "Test log message");
+        log_level!(LogLevel::Info, "Test log message");
+        log_channel!("test_channel", "Test log message 2");
+
+        let traceback = traceback!().to_string();
+        assert!(traceback.contains("[test_channel/Info    ] Test log message 2"));
+        assert!(traceback.contains("[test_channel/Info    ] Test log message "));
+        assert!(traceback.contains("[Info    ] Test log message"));
+    }
+
+    // Test the example in the README
+    #[test]
+    fn read_me_example() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+
+        log!("Hello, world!");
+        log_level!(LogLevel::Info, "Test log message");
+        log_channel!("test_channel", "Test log message");
+        log!(LogLevel::Info, "test_channel", "Test log message");
+    }
+
+    struct MyLogListener2 {
+        success: bool,
+    }
+
+    impl LogListener for MyLogListener2 {
+        fn on_log(&mut self, log: Log) {
+            if log.level.is_at_least(LogLevel::Warn) {
+                self.success = true;
+            }
+        }
+    }
+
+    struct MockLogListenerWrapper2(Arc<Mutex<MyLogListener2>>);
+
+    impl LogListener for MockLogListenerWrapper2 {
+        fn on_log(&mut self, log: Log) {
+            self.0.lock().on_log(log);
+        }
+    }
+
+    #[test]
+    fn no_std_readme_example() {
+        let _guard = TEST_LOCK.lock();
+        let log_handler = Arc::new(Mutex::new(MyLogListener2 { success: false }));
+        let log_handler_wrapper = MockLogListenerWrapper2(log_handler.clone());
+
+        init!(log_handler_wrapper);
+
+        log!(LogLevel::Error, "test_channel", "Test log message");
+
+        assert!(log_handler.lock().success);
+    }
+
+    struct TallyListener(Arc<Mutex<usize>>);
+
+    impl LogListener for TallyListener {
+        fn on_log(&mut self, _log: Log) {
+            *self.0.lock() += 1;
+        }
+    }
+
+    #[test]
+    fn test_multiple_listeners() {
+        let _guard = TEST_LOCK.lock();
+        let first = Arc::new(Mutex::new(0));
+        let second = Arc::new(Mutex::new(0));
+        init!(TallyListener(first.clone()));
+        let second_id = add_listener!(TallyListener(second.clone()));
+
+        log!("Test log message");
+        assert_eq!(*first.lock(), 1);
+        assert_eq!(*second.lock(), 1);
+
+        remove_listener!(second_id);
+        log!("Test log message");
+        assert_eq!(*first.lock(), 2);
+        assert_eq!(*second.lock(), 1);
+
+        init!();
+        log!("Test log message");
+        assert_eq!(*first.lock(), 2);
+    }
+
+    #[test]
+    fn test_log_capacity() {
+        let _guard = TEST_LOCK.lock();
+        init!(capacity = 2);
+        assert_eq!(log_capacity(), Some(2));
+
+        log(None, None, String::from("Log 1"));
+        log(None, None, String::from("Log 2"));
+        log(None, None, String::from("Log 3"));
+        assert_eq!(traceback!().to_string(), "[Info    ] Log 2\n[Info    ] Log 3\n");
+
+        set_log_capacity(1);
+        assert_eq!(traceback!().to_string(), "[Info    ] Log 3\n");
+
+        init!();
+        assert_eq!(log_capacity(), None);
+    }
+
+    #[test]
+    fn test_log_location() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+
+        log!(LogLevel::Info, "test_channel", "Test log message"); let line = line!();
+        log(Some(LogLevel::Info), Some(String::from("test_channel")), String::from("Test log message"));
+
+        let traceback = traceback!();
+        assert_eq!(traceback.0[0].file, Some(file!()));
+        assert_eq!(traceback.0[0].line, Some(line));
+        assert!(traceback.0[0].column.is_some());
+        assert_eq!(traceback.0[1].file, None);
+        assert_eq!(traceback.0[1].line, None);
+        assert_eq!(traceback.0[1].column, None);
+        assert_eq!(format!("{}", traceback.0[0]), format!("[test_channel/Info    ] Test log message @ {}:{}", file!(), line));
+    }
+
+    #[test]
+    fn test_log_metadata() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+
+        log_meta!(LogLevel::Error, "net", "connect failed", { host => "192.168.1.1", port => 443 });
+        log_meta!(LogLevel::Error, "net", "connect failed", { host => "10.0.0.1", port => 80 });
+        log!(LogLevel::Error, "net", "connect failed");
+
+        let traceback = get_logs_traceback(None, None, Some(("port", "443")), None);
+        assert_eq!(traceback.0.len(), 1);
+        assert_eq!(traceback.0[0].metadata, Some(vec![(String::from("host"), String::from("192.168.1.1")), (String::from("port"), String::from("443"))]));
+        assert!(traceback.to_string().contains("[net/Error   ] connect failed host=192.168.1.1 port=443 @ "));
+        assert_eq!(traceback!().0[2].metadata, None);
+    }
+
+    #[test]
+    fn test_global_min_level() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_global_min_level(LogLevel::Warn);
+        assert_eq!(get_global_min_level(), LogLevel::Warn);
+
+        let mut evaluated = false;
+        log!(LogLevel::Info, "test_channel", { evaluated = true; "Filtered log message" });
+        log(Some(LogLevel::Info), None, String::from("Filtered log message"));
+        log!(LogLevel::Error, "test_channel", "Test log message");
+        set_global_min_level(LogLevel::Verbose);
+
+        assert!(!evaluated);
+        let traceback = traceback!();
+        assert_eq!(traceback.0.len(), 1);
+        assert_eq!(traceback.0[0].message, "Test log message");
+    }
+
+    #[test]
+    fn test_traceback_iteration() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!("Log 1");
+        log!("Log 2");
+
+        let traceback = traceback!();
+        assert_eq!(traceback.len(), 2);
+        assert!(!traceback.is_empty());
+        let borrowed: Vec<&str> = (&traceback).into_iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(borrowed, vec!["Log 1", "Log 2"]);
+        let owned: Vec<String> = traceback.into_iter().map(|log| log.message).collect();
+        assert_eq!(owned, vec!["Log 1", "Log 2"]);
+
+        init!();
+        assert!(traceback!().is_empty());
+    }
+
+    #[test]
+    fn test_multi_channel_traceback() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, "sensor", "Log 1");
+        log!(LogLevel::Warn, "actuator", "Log 2");
+        log!(LogLevel::Warn, "network", "Log 3");
+        log!(LogLevel::Info, "sensor", "Log 4");
+
+        let traceback = traceback_channels!(LogLevel::Warn, ["sensor", "actuator"]);
+        let messages: Vec<&str> = traceback.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, vec!["Log 1", "Log 2"]);
+        assert_eq!(traceback!(LogLevel::Warn, ["sensor", "actuator"]).len(), 2);
+        assert_eq!(traceback!(LogLevel::Warn, "sensor").len(), 1);
+    }
+
+    #[test]
+    fn test_traceback_head_and_tail() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Error, "net", "Log 1");
+        log!(LogLevel::Info, "net", "Log 2");
+        log!(LogLevel::Error, "net", "Log 3");
+        log!(LogLevel::Error, "disk", "Log 4");
+
+        let messages = |traceback: Traceback| traceback.into_iter().map(|log| log.message).collect::<Vec<String>>();
+        assert_eq!(messages(traceback_tail!(2)), vec!["Log 3", "Log 4"]);
+        assert_eq!(messages(traceback_head!(2)), vec!["Log 1", "Log 2"]);
+        assert_eq!(messages(traceback_tail!(LogLevel::Error, "net", 1)), vec!["Log 3"]);
+        assert_eq!(messages(traceback_head!(LogLevel::Error, "net", 1)), vec!["Log 1"]);
+        assert_eq!(traceback_tail!(10).len(), 4);
+        assert_eq!(traceback_head!(10).len(), 4);
+    }
+
+    #[test]
+    fn test_log_try_remove() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!("Log 1");
+        log!("Log 2");
+
+        let log = traceback!().0[0].clone();
+        assert_eq!(log.try_remove(), Ok(()));
+        assert_eq!(log.try_remove(), Err(LogNotFound));
+        log.remove();
+        assert_eq!(traceback!().len(), 1);
+    }
+
+    #[test]
+    fn test_log_count() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Error, "motor", "Log 1");
+        log!(LogLevel::Info, "motor", "Log 2");
+        log!(LogLevel::Error, "sensor", "Log 3");
+
+        assert_eq!(log_count(None, None), 3);
+        assert_eq!(log_count(Some(LogLevel::Error), Some(&["motor"])), 1);
+        assert_eq!(log_count(None, Some(&["motor", "sensor"])), 3);
+        assert_eq!(log_count_level(LogLevel::Error), 2);
+        assert_eq!(log_count_channel("motor"), 2);
+        assert_eq!(log_count_channel("network"), 0);
+    }
+
+    struct CollectingListener(Arc<Mutex<Vec<Log>>>);
+
+    impl LogListener for CollectingListener {
+        fn on_log(&mut self, log: Log) {
+            self.0.lock().push(log);
+        }
+    }
+
+    #[test]
+    fn test_deduplication() {
+        let _guard = TEST_LOCK.lock();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        init!(CollectingListener(received.clone()));
+        enable_deduplication();
+
+        for _ in 0..3 {
+            log(Some(LogLevel::Warn), Some(String::from("adc")), String::from("ADC overrange"));
+        }
+        log(Some(LogLevel::Warn), Some(String::from("adc")), String::from("ADC ok"));
+        log(Some(LogLevel::Warn), Some(String::from("adc")), String::from("ADC overrange"));
+        disable_deduplication();
+        log(Some(LogLevel::Warn), Some(String::from("adc")), String::from("ADC overrange"));
+
+        let traceback = traceback!();
+        assert_eq!(traceback.len(), 4);
+        assert_eq!(traceback.0[0].occurrences, 3);
+        assert_eq!(traceback.0[0].to_string(), "[adc/Warn    ] ADC overrange (×3)");
+        assert_eq!(traceback.0[1].occurrences, 1);
+
+        let received = received.lock();
+        assert_eq!(received.len(), 6);
+        assert_eq!(received[2].occurrences, 3);
+    }
+
+    #[test]
+    fn test_log_sequence() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!("Log 1");
+        log!("Log 2");
+        assert_eq!(Log::new(String::from(""), LogLevel::Info, String::from("Log 3")).sequence, 0);
+
+        let mut traceback = traceback!();
+        assert!(traceback.0[0].sequence > 0);
+        assert!(traceback.0[0].sequence < traceback.0[1].sequence);
+
+        traceback.0.reverse();
+        traceback.sort_by_sequence();
+        assert_eq!(traceback.0[0].message, "Log 1");
+    }
+
+    #[test]
+    fn test_clear_logs() {
+        let _guard = TEST_LOCK.lock();
+        let count = Arc::new(Mutex::new(0));
+        init!(TallyListener(count.clone()));
+        log!("Log 1");
+        clear_logs!();
+        assert!(traceback!().is_empty());
+
+        log!("Log 2");
+        assert_eq!(*count.lock(), 2);
+        assert_eq!(traceback!().len(), 1);
+    }
+
+    fn test_clock() -> u64 {
+        1234567
+    }
+
+    #[test]
+    fn test_log_timestamp() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log(Some(LogLevel::Info), Some(String::from("test_channel")), String::from("Log 1"));
+        set_clock(test_clock);
+        log(Some(LogLevel::Info), Some(String::from("test_channel")), String::from("Log 2"));
+        log(Some(LogLevel::Info), None, String::from("Log 3"));
+        clear_clock();
+
+        let traceback = traceback!();
+        assert_eq!(traceback.0[0].timestamp, None);
+        assert_eq!(traceback.0[1].timestamp, Some(1234567));
+        assert_eq!(traceback.to_string(), "[test_channel/Info    ] Log 1\n[1234567/test_channel/Info    ] Log 2\n[1234567/Info    ] Log 3\n");
+    }
+
+    fn boot_clock() -> u64 {
+        set_clock(test_clock);
+        0
+    }
+
+    #[test]
+    fn test_clock_can_replace_itself() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_clock(boot_clock);
+        log!("Log 1");
+        log!("Log 2");
+        clear_clock();
+
+        let traceback = traceback!();
+        assert_eq!(traceback.0[0].timestamp, Some(0));
+        assert_eq!(traceback.0[1].timestamp, Some(1234567));
+    }
+
+    #[test]
+    fn test_closure_listener() {
+        let _guard = TEST_LOCK.lock();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let collected = received.clone();
+        init_with_listener(Box::new(move |log: Log| collected.lock().push(log)));
+
+        log!(LogLevel::Warn, "test_channel", "Test log message");
+
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].message, "Test log message");
+    }
+
+    #[test]
+    fn test_log_format_args() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let x = 42;
+        log!("value is {}", x);
+        log!("values are {} and {}", x, x + 1);
+        log!(LogLevel::Warn, "test_channel", "value is {}", x);
+        log_level!(LogLevel::Warn, "value is {}", x);
+        log_channel!("test_channel", "value is {}", x);
+        log!(LogLevel::Warn, "test_channel", "value is {}");
+
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["value is 42", "values are 42 and 43", "value is 42", "value is 42", "value is 42", "value is {}"]);
+    }
+
+    #[test]
+    fn test_traceback_filter_and_map() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Error, "net", "connect timeout");
+        log!(LogLevel::Info, "net", "connected");
+        log!(LogLevel::Error, "disk", "read timeout");
+
+        let traceback = traceback!()
+            .filter(|log| log.message.contains("timeout"))
+            .map(|mut log| { log.channel = "filtered".into(); log });
+        assert_eq!(traceback.len(), 2);
+        assert!(traceback.iter().all(|log| log.channel == "filtered"));
+        assert_eq!(traceback!().len(), 3);
+    }
+
+    #[test]
+    fn test_traceback_range() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Info, "net", "Log 1");
+        log!(LogLevel::Warn, "net", "Log 2");
+        log!(LogLevel::Error, "disk", "Log 3");
+        log!(LogLevel::Critical, "net", "Log 4");
+
+        let messages = |traceback: Traceback| traceback.into_iter().map(|log| log.message).collect::<Vec<String>>();
+        assert_eq!(messages(traceback_range!(LogLevel::Warn, LogLevel::Error)), vec!["Log 2", "Log 3"]);
+        assert_eq!(messages(traceback_range!(LogLevel::Warn, LogLevel::Error, "net")), vec!["Log 2"]);
+        assert!(traceback_range!(LogLevel::Error, LogLevel::Warn).is_empty());
+    }
+
+    #[test]
+    fn test_drain_logs() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, "net", "Log 1");
+        log!(LogLevel::Info, "net", "Log 2");
+        log!(LogLevel::Warn, "disk", "Log 3");
+
+        let drained = drain_logs(Some(LogLevel::Warn), None);
+        let messages: Vec<String> = drained.into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["Log 1", "Log 3"]);
+        assert_eq!(traceback!().len(), 1);
+        assert_eq!(drain_all_logs().len(), 1);
+        assert!(traceback!().is_empty());
+    }
+
+    struct FlushListener(Arc<Mutex<usize>>);
+
+    impl LogListener for FlushListener {
+        fn on_log(&mut self, _log: Log) {}
+
+        fn on_flush(&mut self) {
+            *self.0.lock() += 1;
+            log!("=== log store flushed ===");
+        }
+    }
+
+    #[test]
+    fn test_listener_on_flush() {
+        let _guard = TEST_LOCK.lock();
+        let flushes = Arc::new(Mutex::new(0));
+        init!(FlushListener(flushes.clone()));
+        log!("Log 1");
+        clear_logs!();
+
+        assert_eq!(*flushes.lock(), 1);
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["=== log store flushed ==="]);
+
+        init!();
+        assert_eq!(*flushes.lock(), 2);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_rate_limit("irq", 2, 5);
+        for i in 0..7 {
+            log(Some(LogLevel::Info), Some(String::from("irq")), format!("IRQ {}", i));
+        }
+        log(Some(LogLevel::Info), Some(String::from("other")), String::from("Not limited"));
+        clear_rate_limits();
+
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["IRQ 0", "IRQ 1", "[irq] rate limit: 3 messages suppressed", "IRQ 5", "IRQ 6", "Not limited"]);
+    }
+
+    enum TestChannel {
+        Network,
+        Storage,
+    }
+
+    impl LogChannel for TestChannel {
+        fn channel_name(&self) -> &'static str {
+            match self {
+                TestChannel::Network => "Network",
+                TestChannel::Storage => "Storage",
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_log_channel() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log_to_channel(Some(LogLevel::Info), TestChannel::Network, String::from("Connected"));
+        log!(LogLevel::Warn, TestChannel::Storage, "Disk at {}%", 95);
+        log_channel!(TestChannel::Network, "Disconnected");
+        log!(LogLevel::Info, String::from("Network"), "Reconnected");
+        let traceback = traceback_channel!("Network");
+        assert_eq!(traceback.len(), 3);
+        assert_eq!(traceback_channel!("Storage").0[0].message, "Disk at 95%");
+    }
+
+    #[test]
+    fn test_try_clone_log() {
+        let mut log = Log::new_warn("net", "Slow").with_context("rtt", "350");
+        log.extra_channels.push(String::from("wifi"));
+        log.file = Some(file!());
+        log.sequence = 7;
+        assert_eq!(try_clone_log(&log), Some(log));
+    }
+
+    #[cfg(feature = "critical_section")]
+    #[test]
+    fn test_global_mutex_skips_reentrant_use() {
+        let mutex = GlobalMutex::new(1);
+        let nested = mutex.with(|value| {
+            *value += 1;
+            (mutex.try_with(|value| *value), mutex.with(|value| *value))
+        });
+        assert_eq!(nested, (None, 0));
+        assert_eq!(mutex.with(|value| *value), 2);
+    }
+
+    #[cfg(feature = "critical_section")]
+    #[test]
+    fn test_listener_logging_reentrantly_does_not_panic() {
+        let _guard = TEST_LOCK.lock();
+        init_with_listener(Box::new(|log: Log| {
+            if log.message == "Outer" {
+                log!("Inner");
+            }
+        }));
+        log!("Outer");
+        assert_eq!(traceback!().len(), 2);
+        init!();
+    }
+
+    #[cfg(feature = "log-facade")]
+    #[test]
+    fn test_log_facade() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        init_as_global_logger(::log::LevelFilter::Debug).unwrap();
+        assert!(init_as_global_logger(::log::LevelFilter::Debug).is_err());
+        ::log::error!(target: "net", "Link down on {}", "eth0");
+        ::log::trace!(target: "net", "Filtered by the facade");
+        silence_channel("noisy");
+        ::log::info!(target: "noisy", "Silenced");
+        unsilence_channel("noisy");
+
+        let traceback = traceback!();
+        assert_eq!(traceback.len(), 1);
+        assert_eq!(traceback.0[0].channel, "net");
+        assert_eq!(traceback.0[0].level, LogLevel::Error);
+        assert_eq!(traceback.0[0].message, "Link down on eth0");
+        assert_eq!(traceback.0[0].file, Some(file!()));
+    }
+
+    #[test]
+    fn test_log_once() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        for i in 0..5 {
+            log_once!(LogLevel::Error, "hw", "DMA stall detected");
+            log_once!(LogLevel::Warn, "hw", "Retry {}", i);
+        }
+        let traceback = traceback_channel!("hw");
+        assert_eq!(traceback.len(), 2);
+        assert_eq!(traceback.0[0].message, "DMA stall detected");
+        assert_eq!(traceback.0[1].message, "Retry 0");
+
+        set_global_min_level(LogLevel::Error);
+        for _ in 0..2 {
+            log_once!(LogLevel::Info, "hw", "Suppressed");
+            set_global_min_level(LogLevel::Verbose);
+        }
+        assert_eq!(traceback_channel!("hw").len(), 3);
+
+        silence_channel("hw");
+        for _ in 0..2 {
+            log_once!(LogLevel::Info, "hw", "Silenced");
+            unsilence_channel("hw");
+        }
+        assert_eq!(traceback_channel!("hw").0.last().unwrap().message, "Silenced");
+    }
+
+    struct RemovalListener(Arc<Mutex<Vec<Log>>>);
+
+    impl LogListener for RemovalListener {
+        fn on_log(&mut self, log: Log) {
+            if log.message == "Discard me" {
+                log.remove();
+            }
+        }
+
+        fn on_remove(&mut self, log: &Log) {
+            self.0.lock().push(log.clone());
+        }
+    }
+
+    #[test]
+    fn test_listener_on_remove() {
+        let _guard = TEST_LOCK.lock();
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        init!(RemovalListener(removed.clone()));
+        log!("Discard me");
+        log!("Keep me");
+        assert_eq!(traceback!().len(), 1);
+        assert_eq!(removed.lock().len(), 1);
+        assert_eq!(removed.lock()[0].message, "Discard me");
+
+        let log = traceback!().0[0].clone();
+        log.remove();
+        assert_eq!(removed.lock()[1], log);
+        assert_eq!(log.try_remove(), Err(LogNotFound));
+        assert_eq!(removed.lock().len(), 2);
+    }
+
+    #[test]
+    fn test_traceback_diff() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log_channel!("dma", "Started");
+        log_channel!("dma", "Stalled");
+        let before = traceback!();
+        before.0[1].remove();
+        log_channel!("dma", "Stalled");
+        let after = traceback!();
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.removed, vec![before.0[1].clone()]);
+        assert_eq!(diff.added, vec![after.0[1].clone()]);
+        assert!(before.diff(&before).is_empty());
+
+        let unsequenced = Log::new(String::from("dma"), LogLevel::Info, String::from("Stalled"));
+        let diff = Traceback(vec![unsequenced.clone()]).diff(&Traceback(vec![unsequenced.clone()]));
+        assert!(diff.is_empty());
+        let diff = Traceback(vec![]).diff(&Traceback(vec![unsequenced.clone()]));
+        assert_eq!(diff.to_string(), "+ [dma/Info    ] Stalled\n");
+    }
+
+    #[test]
+    fn test_last_log() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        assert_eq!(last_log!(), None);
+        log!(LogLevel::Error, "motor", "Stalled");
+        log!(LogLevel::Info, "sensor", "Calibrated");
+        log!(LogLevel::Warn, "sensor", "Drifting");
+        log!(LogLevel::Verbose, "motor", "Tick");
+        assert_eq!(last_log!().unwrap().message, "Tick");
+        assert_eq!(last_log!(LogLevel::Warn).unwrap().message, "Drifting");
+        assert_eq!(last_log_channel!("motor").unwrap().message, "Tick");
+        assert_eq!(last_log_channel!("motor", LogLevel::Info).unwrap().message, "Stalled");
+        assert_eq!(last_log_channel!("sensor", LogLevel::Error), None);
+        assert_eq!(last_log_channel!("actuator"), None);
+    }
+
+    #[test]
+    fn test_listener_priority() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for (name, priority) in [("buffer", 0), ("alert", 10), ("metrics", 0), ("audit", -5), ("pager", 10)] {
+            let order = order.clone();
+            add_listener!(move |_: Log| order.lock().push(name), priority = priority);
+        }
+        let order_default = order.clone();
+        add_listener!(move |_: Log| order_default.lock().push("default"));
+        log!("Test log message");
+        assert_eq!(*order.lock(), vec!["alert", "pager", "buffer", "metrics", "default", "audit"]);
+    }
+
+    #[derive(Default)]
+    struct RingStore {
+        logs: [Option<Log>; 4],
+        start: usize,
+        len: usize,
+    }
+
+    impl LogStore for RingStore {
+        fn push(&mut self, log: Log) {
+            if self.len == self.logs.len() {
+                self.remove_at(0);
+            }
+            self.logs[(self.start + self.len) % 4] = Some(log);
+            self.len += 1;
+        }
+
+        fn iter<'a>(&'a self) -> Box<dyn DoubleEndedIterator<Item = &'a Log> + 'a> {
+            Box::new((0..self.len).filter_map(move |i| self.logs[(self.start + i) % 4].as_ref()))
+        }
+
+        fn remove_at(&mut self, idx: usize) {
+            for i in idx..self.len - 1 {
+                self.logs[(self.start + i) % 4] = self.logs[(self.start + i + 1) % 4].take();
+            }
+            self.logs[(self.start + self.len - 1) % 4] = None;
+            self.len -= 1;
+        }
+
+        fn clear(&mut self) {
+            *self = RingStore::default();
+        }
+    }
+
+    #[test]
+    fn test_custom_log_store() {
+        let _guard = TEST_LOCK.lock();
+        set_log_store(Box::new(RingStore::default()));
+        init!();
+        for i in 0..6 {
+            log!(LogLevel::Info, "ring", "Log {}", i);
+        }
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["Log 2", "Log 3", "Log 4", "Log 5"]);
+
+        traceback!().0[1].remove();
+        assert_eq!(get_last_log(None).unwrap().message, "Log 5");
+        assert_eq!(drain_logs(None, None).len(), 3);
+        assert!(traceback!().is_empty());
+        set_log_store(Box::new(VecLogStore::new()));
+    }
+
+    #[test]
+    fn test_level_macros() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log_verbose!("levels", "Verbose message");
+        log_debug!("levels", "Debug message {}", 1);
+        log_info!("levels", "Info message");
+        log_warn!("levels", "Warn message");
+        log_error!("levels", "Error message");
+        log_critical!("levels", "Critical message {}", 5);
+        let levels: Vec<LogLevel> = traceback_channel!("levels").into_iter().map(|log| log.level).collect();
+        assert_eq!(levels, vec![LogLevel::Verbose, LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error, LogLevel::Critical]);
+        assert_eq!(traceback_channel!("levels").0[5].message, "Critical message 5");
+    }
+
+    #[test]
+    fn test_traceback_group_by_channel() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, "net", "Timeout");
+        log!(LogLevel::Info, "disk", "Mounted");
+        log!(LogLevel::Error, "net", "Disconnected");
+        log!(LogLevel::Info, "net", "Reconnected");
+
+        let traceback = traceback_level!(LogLevel::Warn);
+        assert_eq!(traceback.channels(), vec![String::from("net")]);
+        let groups = traceback!().group_by_channel();
+        assert_eq!(groups.iter().map(|(channel, _)| channel.as_str()).collect::<Vec<_>>(), vec!["net", "disk"]);
+        let messages: Vec<&str> = groups[0].1.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, vec!["Timeout", "Disconnected", "Reconnected"]);
+        assert!(Traceback(vec![]).group_by_channel().is_empty());
+    }
+
+    #[test]
+    fn test_channel_sample_rate() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_channel_sample_rate("telemetry", 3);
+        set_channel_sample_rate("noise", 0);
+        assert_eq!(get_channel_sample_rate("telemetry"), 3);
+        assert_eq!(get_channel_sample_rate("other"), 1);
+        for i in 0..7 {
+            log!(LogLevel::Info, "telemetry", "Sample {}", i);
+            log!(LogLevel::Info, "noise", "Noise {}", i);
+        }
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["Sample 0", "Sample 3", "Sample 6"]);
+
+        set_rate_limit("telemetry", 1, 100);
+        clear_rate_limits();
+        assert_eq!(get_channel_sample_rate("telemetry"), 3);
+        set_channel_sample_rate("telemetry", 1);
+        set_channel_sample_rate("noise", 1);
+        clear_rate_limits();
+        log!(LogLevel::Info, "noise", "Audible");
+        assert_eq!(traceback_channel!("noise").len(), 1);
+    }
+
+    #[test]
+    fn test_log_key() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        enable_deduplication();
+        log!(LogLevel::Warn, "net", "Retrying");
+        let log = traceback!().0[0].clone();
+        log!(LogLevel::Warn, "net", "Retrying");
+        assert_ne!(traceback!().0[0], log);
+        assert_eq!(traceback!().0[0].key(), log.key());
+        assert_eq!(log.try_remove(), Ok(()));
+        assert!(traceback!().is_empty());
+        disable_deduplication();
+
+        let other = Log::new(String::from("net"), LogLevel::Error, String::from("Retrying"));
+        assert_ne!(log.key(), other.key());
+    }
+
+    #[test]
+    fn test_silence_channel() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        silence_channel("noisy");
+        log!(LogLevel::Error, "noisy", "Muted");
+        log_meta!(LogLevel::Error, "noisy", "Muted", { code => 1 });
+        log(Some(LogLevel::Error), Some(String::from("noisy")), String::from("Muted"));
+        log!(LogLevel::Error, "quiet", "Audible");
+        assert_eq!(traceback!().len(), 1);
+        unsilence_channel("noisy");
+        log!(LogLevel::Error, "noisy", "Audible");
+        assert_eq!(traceback!().len(), 2);
+
+        silence_all();
+        log!("Muted");
+        log!(LogLevel::Error, "quiet", "Muted");
+        assert_eq!(traceback!().len(), 2);
+        silence_channel("noisy");
+        unsilence_all();
+        assert!(!is_channel_silenced("noisy"));
+        log!("Audible");
+        assert_eq!(traceback!().len(), 3);
+    }
+
+    #[test]
+    fn test_traceback_to_csv() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_clock(test_clock);
+        log!(LogLevel::Warn, "net,eth0", "Link\ndown");
+        clear_clock();
+        let traceback = traceback!();
+        let sequence = traceback.0[0].sequence;
+        assert_eq!(
+            traceback.to_csv(),
+            format!("sequence,timestamp,channel,level,message\r\n{},1234567,\"net,eth0\",Warn,\"Link\ndown\"\r\n", sequence)
+        );
+        assert_eq!(Traceback(vec![]).to_csv(), "sequence,timestamp,channel,level,message\r\n");
+    }
+
+    #[test]
+    fn test_copy_logs_to_slice() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let empty = Log::new(String::new(), LogLevel::Info, String::new());
+        let mut buf = [empty.clone(), empty.clone(), empty.clone()];
+        assert_eq!(copy_logs_to_slice(&mut buf), 0);
+        log!("Log 1");
+        log!("Log 2");
+        assert_eq!(copy_logs_to_slice(&mut buf), 2);
+        assert_eq!(buf[0], traceback!().0[0]);
+        assert_eq!(buf[2], empty);
+        assert_eq!(copy_logs_to_slice(&mut []), 0);
+    }
+
+    #[cfg(feature = "panic-hook")]
+    #[test]
+    fn test_panic_hook() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        install_panic_hook();
+        let result = std::panic::catch_unwind(|| panic!("Sensor {} offline", 3));
+        assert!(result.is_err());
+        let log = last_log_channel!("panic").unwrap();
+        assert_eq!(log.level, LogLevel::Critical);
+        assert!(log.message.starts_with("Sensor 3 offline at src/lib.rs:"));
+    }
+
+    #[cfg(feature = "panic-hook")]
+    #[test]
+    fn test_panic_hook_skips_panics_inside_listeners() {
+        let _guard = TEST_LOCK.lock();
+        init_with_listener(Box::new(|log: Log| {
+            if log.message == "Crash" {
+                panic!("Listener crashed");
+            }
+        }));
+        install_panic_hook();
+        assert!(std::panic::catch_unwind(|| log!("Crash")).is_err());
+        assert!(traceback_channel!("panic").is_empty());
+
+        let thread = std::thread::spawn(|| panic!("Worker crashed"));
+        assert!(thread.join().is_err());
+        assert!(std::panic::catch_unwind(|| panic!("Sensor offline")).is_err());
+        let messages: Vec<String> = traceback_channel!("panic").0.into_iter().map(|log| log.message).collect();
+        assert!(messages.iter().any(|message| message.starts_with("Worker crashed")));
+        assert!(messages.iter().any(|message| message.starts_with("Sensor offline")));
+        init!();
+    }
+
+    #[test]
+    fn test_batch_listener() {
+        let _guard = TEST_LOCK.lock();
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let mut batch = BatchListener::new(CollectingListener(logs.clone()));
+        init!(batch.clone());
+        log!("Log 1");
+        log!("Log 2");
+        assert!(logs.lock().is_empty());
+        batch.flush();
+        assert_eq!(*logs.lock(), traceback!().0);
+        log!("Log 3");
+        batch.flush();
+        batch.flush();
+        assert_eq!(logs.lock().len(), 3);
+    }
+
+    #[test]
+    fn test_traceback_excluding() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, "noisy", "Tick");
+        log!(LogLevel::Warn, "telemetry", "Sample");
+        log!(LogLevel::Warn, "motor", "Stalled");
+        log!(LogLevel::Info, "motor", "Retrying");
+        assert_eq!(traceback_excluding!(LogLevel::Verbose, "noisy").len(), 3);
+        let traceback = traceback_excluding!(LogLevel::Warn, ["noisy", "telemetry"]);
+        assert_eq!(traceback.len(), 1);
+        assert_eq!(traceback.0[0].message, "Stalled");
+        assert_eq!(get_logs_traceback_excluding(None, None).len(), 4);
+    }
+
+    #[test]
+    fn test_level_filter() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Verbose, "motor", "Tick");
+        log!(LogLevel::Error, "motor", "Stalled");
+        assert_eq!(get_logs_traceback(LevelFilter::All, None, None, None).len(), 2);
+        assert_eq!(get_logs_traceback(LogLevel::all(), None, None, None).len(), 2);
+        assert_eq!(get_logs_traceback(LogLevel::Warn, None, None, None).len(), 1);
+        assert!(get_logs_traceback(LevelFilter::None, None, None, None).is_empty());
+        assert_eq!(log_count(LevelFilter::None, None), 0);
+        assert_eq!(log_count(None, Some(&["motor"])), 2);
+        assert_eq!(get_last_log(LevelFilter::None), None);
+        assert!(drain_logs(LevelFilter::None, None).is_empty());
+        assert_eq!(drain_logs(LevelFilter::AtLeast(LogLevel::Error), None).len(), 1);
+    }
+
+    #[test]
+    fn test_traceback_sorts() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Info, "net", "Log 1");
+        log!(LogLevel::Critical, "disk", "Log 2");
+        log!(LogLevel::Info, "adc", "Log 3");
+        log!(LogLevel::Warn, "net", "Log 4");
+        let messages = |traceback: &Traceback| traceback.iter().map(|log| log.message.clone()).collect::<Vec<_>>();
+
+        let mut traceback = traceback!();
+        traceback.sort_by_level();
+        assert_eq!(messages(&traceback), vec!["Log 2", "Log 4", "Log 1", "Log 3"]);
+        traceback.sort_by_level_asc();
+        assert_eq!(messages(&traceback), vec!["Log 1", "Log 3", "Log 4", "Log 2"]);
+        traceback.sort_by_channel();
+        assert_eq!(messages(&traceback), vec!["Log 3", "Log 2", "Log 1", "Log 4"]);
+        traceback.sort_by_sequence();
+        assert_eq!(traceback, traceback!());
+    }
+
+    #[test]
+    fn test_init_append() {
+        let _guard = TEST_LOCK.lock();
+        init!(capacity = 8);
+        log!("Early boot log");
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        init_append(Box::new(CollectingListener(logs.clone())));
+        init_append_no_listener();
+        log!("Application log");
+        assert_eq!(traceback!().len(), 2);
+        assert_eq!(logs.lock().len(), 1);
+        assert_eq!(log_capacity(), Some(8));
+    }
+
+    #[test]
+    fn test_traceback_add() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!("Before");
+        let before = traceback!();
+        log!("After");
+        let after = traceback!();
+        let mut report = before.clone() + after.clone();
+        assert_eq!(report.len(), 3);
+        assert_eq!(report.0[0], report.0[1]);
+        report += before;
+        assert_eq!(report.len(), 4);
+        assert_eq!(report.0[3].message, "Before");
+        assert_eq!(Traceback::merge(Traceback(vec![]), after.clone()), after);
+    }
+
+    struct InitListener(Arc<Mutex<usize>>);
+
+    impl LogListener for InitListener {
+        fn on_log(&mut self, _log: Log) {}
+
+        fn on_init(&mut self) {
+            *self.0.lock() += 1;
+            log!("=== logging started ===");
+        }
+    }
+
+    #[test]
+    fn test_listener_on_init() {
+        let _guard = TEST_LOCK.lock();
+        let count = Arc::new(Mutex::new(0));
+        init!(InitListener(count.clone()));
+        assert_eq!(*count.lock(), 1);
+        assert_eq!(traceback!().0[0].message, "=== logging started ===");
+        init_append(Box::new(InitListener(count.clone())));
+        assert_eq!(*count.lock(), 2);
+        assert_eq!(traceback!().len(), 2);
+        init!();
+        assert_eq!(*count.lock(), 2);
+    }
+
+    #[test]
+    fn test_log_len() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        assert_eq!(log_len(), 0);
+        log!("Log 1");
+        log!("Log 2");
+        log!("Log 3");
+        assert_eq!(log_len(), 3);
+        traceback!().0[0].remove();
+        assert_eq!(log_len(), 2);
+        set_log_capacity(1);
+        assert_eq!(log_len(), 1);
+        log!("Log 4");
+        assert_eq!(log_len(), 1);
+        init!();
+        log!(LogLevel::Warn, "net", "Log 5");
+        log!("Log 6");
+        drain_logs(LogLevel::Warn, None);
+        assert_eq!(log_len(), traceback!().len());
+        clear_logs();
+        assert_eq!(log_len(), 0);
+    }
+
+    #[test]
+    fn test_log_if() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let mut evaluated = 0;
+        let mut message = |text: &str| {
+            evaluated += 1;
+            String::from(text)
+        };
+        for i in 0..4 {
+            log_if!(i == 2, LogLevel::Error, "loop", message("Reached two"));
+        }
+        assert_eq!(evaluated, 1);
+        log_if!(false, LogLevel::Error, "loop", "Skipped {}", 1);
+        log_if!(true, LogLevel::Error, "loop", "Value {}", 3);
+        let messages: Vec<String> = traceback_channel!("loop").into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["Reached two", "Value 3"]);
+    }
+
+    #[test]
+    fn test_log_age_ticks() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!("Untimed");
+        set_clock(test_clock);
+        log!("Timed");
+        let mut traceback = traceback!();
+        traceback.0[1].timestamp = Some(1234000);
+        assert_eq!(traceback.0[0].age_ticks(), None);
+        assert_eq!(traceback.0[1].age_ticks(), Some(567));
+        assert_eq!(traceback.logs_older_than(500).len(), 1);
+        assert!(traceback.logs_older_than(567).is_empty());
+        assert_eq!(traceback!().0[1].age_ticks(), Some(0));
+        clear_clock();
+        assert!(traceback.logs_older_than(0).is_empty());
+    }
+
+    #[test]
+    fn test_log_assert() {
+        let _guard = TEST_LOCK.lock();
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        init!(CollectingListener(logs.clone()));
+        silence_channel("power");
+        log_assert!(true, "power", "Never logged");
+        let voltage = 2;
+        let result = std::panic::catch_unwind(|| log_assert!(voltage > 3, "power", "Voltage is {}", voltage));
+        unsilence_channel("power");
+        assert!(result.is_err());
+        assert_eq!(logs.lock().len(), 1);
+        let log = last_log!().unwrap();
+        assert_eq!(log.level, LogLevel::Critical);
+        assert_eq!(log.message, "Voltage is 2");
+
+        let result = std::panic::catch_unwind(|| log_assert!(voltage == 3));
+        assert!(result.is_err());
+        assert_eq!(last_log!().unwrap().message, "assertion failed: voltage == 3");
+    }
+
+    #[test]
+    fn test_tee_listener() {
+        let _guard = TEST_LOCK.lock();
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let third = Arc::new(Mutex::new(Vec::new()));
+        init!(TeeListener::new(CollectingListener(first.clone()), CollectingListener(second.clone())));
+        add_listener(tee_listeners!(CollectingListener(third.clone()), |_: Log| {}));
+        log!("Log 1");
+        log!("Log 2");
+        assert_eq!(first.lock().len(), 2);
+        assert_eq!(*first.lock(), *second.lock());
+        assert_eq!(*first.lock(), *third.lock());
+    }
+
+    #[test]
+    fn test_filter_listener() {
+        let _guard = TEST_LOCK.lock();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let everything = Arc::new(Mutex::new(Vec::new()));
+        init!(FilterListener::new(CollectingListener(errors.clone()), |log: &Log| log.level.is_at_least(LogLevel::Error)));
+        add_listener!(CollectingListener(everything.clone()));
+        log!(LogLevel::Info, "motor", "Started");
+        log!(LogLevel::Error, "motor", "Stalled");
+        log!(LogLevel::Warn, "motor", "Hot");
+        assert_eq!(everything.lock().len(), 3);
+        assert_eq!(errors.lock().len(), 1);
+        assert_eq!(errors.lock()[0].message, "Stalled");
+    }
+
+    #[test]
+    fn test_counting_listener() {
+        let _guard = TEST_LOCK.lock();
+        let counter = Arc::new(Mutex::new(CountingListener::new()));
+        init!(counter.clone());
+        log!(LogLevel::Error, "motor", "Stalled");
+        log!(LogLevel::Error, "net", "Timeout");
+        log!(LogLevel::Info, "motor", "Started");
+        assert_eq!(counter.lock().error_count(), 2);
+        assert_eq!(counter.lock().count_for_level(LogLevel::Info), 1);
+        assert_eq!(counter.lock().count_for_level(LogLevel::Warn), 0);
+        assert_eq!(counter.lock().count_for_channel("motor"), 2);
+        assert_eq!(counter.lock().count_for_channel("net"), 1);
+        counter.lock().reset_counts();
+        assert_eq!(counter.lock().error_count(), 0);
+        log!(LogLevel::Warn, "motor", "Hot");
+        assert_eq!(counter.lock().count_for_channel("motor"), 1);
+        log!(LogLevel::Warn, ["motor", "net"], "Overheating");
+        assert_eq!(counter.lock().count_for_channel("motor"), 2);
+        assert_eq!(counter.lock().count_for_channel("net"), 1);
+        assert_eq!(counter.lock().count_for_level(LogLevel::Warn), 2);
+    }
+
+    #[test]
+    fn test_logger_is_independent_of_globals() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let logger = Logger::with_capacity(2);
+        logger.set_listener(Box::new(CollectingListener(seen.clone())));
+        set_global_min_level(LogLevel::Critical);
+        local_log!(&logger, LogLevel::Info, "motor", "Started");
+        local_log!(&logger, LogLevel::Error, "motor", "Stalled at {}", 3);
+        local_log!(&logger, "No channel");
+        set_global_min_level(LogLevel::Verbose);
+        assert!(traceback!().is_empty());
+        let traceback = logger.traceback(None, None);
+        assert_eq!(traceback.len(), 2);
+        assert_eq!(traceback.0[0].message, "Stalled at 3");
+        assert_eq!(traceback.0[0].sequence, 2);
+        assert_eq!(traceback.0[1].channel, "");
+        assert_eq!(seen.lock().len(), 3);
+        assert_eq!(logger.drain_logs(LogLevel::Error, None).len(), 1);
+        logger.clear_logs();
+        assert!(logger.is_empty());
+    }
+
+    #[test]
+    fn test_traceback_max_results() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Info, "motor", "Started");
+        log!(LogLevel::Error, "motor", "Stalled");
+        log!(LogLevel::Error, "motor", "Stalled again");
+        log!(LogLevel::Error, "motor", "Gave up");
+        let traceback = get_logs_traceback(LogLevel::Error, None, None, Some(2));
+        assert_eq!(traceback.len(), 2);
+        assert_eq!(traceback.0[0].message, "Stalled");
+        assert_eq!(traceback.0[1].message, "Stalled again");
+        assert_eq!(traceback_max!(10).len(), 4);
+        assert!(traceback_max!(0).is_empty());
+    }
+
+    #[test]
+    fn test_init_with_capacity_and_listener() {
+        let _guard = TEST_LOCK.lock();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        init!(capacity = 2, CollectingListener(seen.clone()));
+        assert_eq!(log_capacity(), Some(2));
+        log!("First");
+        log!("Second");
+        log!("Third");
+        assert_eq!(seen.lock().len(), 3);
+        let traceback = traceback!();
+        assert_eq!(traceback.len(), 2);
+        assert_eq!(traceback.0[0].message, "Second");
+        init!();
+    }
+
+    #[test]
+    fn test_max_message_length() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_max_message_length(80);
+        log!(LogLevel::Info, "bulk", "x".repeat(10_000));
+        log!(LogLevel::Info, "bulk", "é".repeat(100));
+        log!(LogLevel::Info, "bulk", "short");
+        clear_max_message_length();
+        let traceback = traceback!();
+        assert_eq!(traceback.0[0].message.len(), 80 + "…".len());
+        assert!(traceback.0[0].message.ends_with('…'));
+        assert_eq!(traceback.0[1].message, "é".repeat(40) + "…");
+        assert_eq!(traceback.0[2].message, "short");
+    }
+
+    #[test]
+    fn test_log_lazy_skips_filtered_messages() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let calls = Arc::new(Mutex::new(0));
+        let build = |message: &str| {
+            *calls.lock() += 1;
+            message.to_string()
+        };
+        set_global_min_level(LogLevel::Info);
+        log_lazy!(LogLevel::Verbose, "sensor", || build("Raw dump"));
+        silence_channel("sensor");
+        log_lazy!(LogLevel::Error, "sensor", || build("Silenced"));
+        unsilence_channel("sensor");
+        log_lazy!(LogLevel::Error, "sensor", || build("Stuck"));
+        set_global_min_level(LogLevel::Verbose);
+        assert_eq!(*calls.lock(), 1);
+        let traceback = traceback!();
+        assert_eq!(traceback.len(), 1);
+        assert_eq!(traceback.0[0].message, "Stuck");
+    }
+
+    #[test]
+    fn test_log_prefix() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_log_prefix("[IMG0] ");
+        set_max_message_length(10);
+        log!(LogLevel::Info, "boot", "Booted");
+        log!(LogLevel::Info, "boot", "Loaded all drivers");
+        clear_max_message_length();
+        clear_log_prefix();
+        log!(LogLevel::Info, "boot", "Ready");
+        let traceback = traceback!();
+        assert_eq!(traceback.0[0].message, "[IMG0] Boo…");
+        assert_eq!(traceback.0[1].message, "[IMG0] Loa…");
+        assert_eq!(traceback.0[2].message, "Ready");
+    }
 
+    static OVERFLOWED: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
+    fn record_overflow(dropped: &Log) {
+        OVERFLOWED.lock().push(dropped.message.clone());
+    }
 
-/// A macro for logging messages with an optional log level and channel.
-/// 
-/// To only specify a `LogLevel`, use the `log_level!` macro.
-/// 
-/// To only specify a `channel`, use the `log_channel!` macro.
-/// 
-/// # Examples
-/// 
-/// Log with a log level, channel and message
-/// ```rust
-/// use breadcrumbs::{log, LogLevel};
-/// log!(LogLevel::Info, "test_channel", "Test log message");
-/// ```
-/// 
-/// Log with just a message
-/// 
-/// ```rust
-/// use breadcrumbs::log;
-/// log!("Test log message");
-/// ```
-#[macro_export]
-macro_rules! log {
-    ($arg1:expr, $arg2:expr, $arg3:expr) => {
-        $crate::log(Some($arg1), Some($arg2.to_string()), $arg3.to_string())
-    };
-    ($arg1:expr) => {
-        $crate::log(None, None, $arg1.to_string())
-    };
-}
+    #[test]
+    fn test_overflow_handler() {
+        let _guard = TEST_LOCK.lock();
+        init!(capacity = 2);
+        OVERFLOWED.lock().clear();
+        set_overflow_handler(record_overflow);
+        log!("First");
+        log!("Second");
+        log!("Third");
+        log!("Fourth");
+        clear_overflow_handler();
+        assert_eq!(*OVERFLOWED.lock(), vec!["First".to_string(), "Second".to_string()]);
+        assert_eq!(get_overflow_count(), 2);
+        init!(capacity = 0);
+        log!("Dropped");
+        assert_eq!(get_overflow_count(), 1);
+        init!();
+        assert_eq!(get_overflow_count(), 0);
+    }
 
-/// A macro for logging messages with a log level only.
-/// 
-/// # Examples
-/// 
-/// ```rust
-/// use breadcrumbs::{log_level, LogLevel};
-/// log_level!(LogLevel::Info, "Test log message");
-/// ```
-#[macro_export]
-macro_rules! log_level {
-    ($arg1:expr, $arg2:expr) => {
-        $crate::log(Some($arg1), None, $arg2.to_string())
-    };
-}
+    #[test]
+    fn test_multi_channel_logs() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, ["network", "security"], "Port scan from {}", "10.0.0.7");
+        log!(LogLevel::Info, "network", "Connected");
+        assert_eq!(traceback_channel!("security").len(), 1);
+        assert_eq!(traceback_channel!("network").len(), 2);
+        assert_eq!(get_logs_traceback_excluding(None, Some(vec!["security".to_string()])).len(), 1);
+        let log = traceback_channel!("security").0[0].clone();
+        assert_eq!(log.channel, "network");
+        assert_eq!(log.extra_channels, vec!["security".to_string()]);
+        let display = format!("{}", Log::new_multi(log.channels().map(String::from).collect(), log.level, log.message.clone()));
+        assert_eq!(display, "[network,security/Warn    ] Port scan from 10.0.0.7");
+        assert_eq!(Log::try_from(display.as_str()).unwrap().extra_channels, vec!["security".to_string()]);
+        silence_channel("network");
+        log!(LogLevel::Warn, ["network", "security"], "Still stored");
+        log!(LogLevel::Warn, ["network"], "Dropped");
+        unsilence_channel("network");
+        assert_eq!(traceback!().len(), 3);
+    }
 
-/// A macro for logging messages with a channel only.
-/// 
-/// # Examples
-/// 
-/// ```rust
-/// use breadcrumbs::{log_channel, LogLevel};
-/// log_channel!("test_channel", "Test log message");
-/// ```
-#[macro_export]
-macro_rules! log_channel {
-    ($arg1:expr, $arg2:expr) => {
-        $crate::log(None, Some($arg1.to_string()), $arg2.to_string())
-    };
-}
+    #[test]
+    fn test_scope_log() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        {
+            scope_log!("fw", "outer");
+            {
+                scope_log!("fw", "inner");
+                log!(LogLevel::Info, "fw", "Working");
+            }
+        }
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["→ outer", "→ inner", "Working", "← inner", "← outer"]);
+        assert!(traceback!().iter().all(|log| log.file == Some(file!())));
+        set_global_min_level(LogLevel::Info);
+        {
+            scope_log!("fw", "quiet");
+        }
+        set_global_min_level(LogLevel::Verbose);
+        assert_eq!(traceback!().len(), 5);
+    }
+
+    #[test]
+    fn test_deduplicate_all() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, "net", "Retrying");
+        log!(LogLevel::Info, "net", "Connected");
+        log!(LogLevel::Warn, "net", "Retrying");
+        log!(LogLevel::Warn, ["net", "security"], "Retrying");
+        let unique = traceback!().deduplicate_all();
+        assert_eq!(unique.len(), 3);
+        assert_eq!(unique.0[0].sequence, traceback!().0[0].sequence);
+        assert_eq!(unique.0[2].extra_channels, vec!["security".to_string()]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_into_unique_set() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        log!(LogLevel::Warn, "net", "Retrying");
+        log!(LogLevel::Warn, "net", "Retrying");
+        let mut traceback = traceback!();
+        traceback.0.push(traceback.0[0].clone());
+        let set = traceback.into_unique_set();
+        assert_eq!(set.len(), 2);
+    }
+
+    struct WarnOnlyListener(Arc<Mutex<Vec<Log>>>);
+
+    impl LogListener for WarnOnlyListener {
+        fn on_log(&mut self, log: Log) {
+            self.0.lock().push(log);
+        }
+
+        fn filter_hook(&self, log: &Log) -> bool {
+            log.level == LogLevel::Warn
+        }
+    }
+
+    #[test]
+    fn test_filter_hook() {
+        let _guard = TEST_LOCK.lock();
+        let direct = Arc::new(Mutex::new(Vec::new()));
+        let teed = Arc::new(Mutex::new(Vec::new()));
+        let everything = Arc::new(Mutex::new(Vec::new()));
+        init!(WarnOnlyListener(direct.clone()));
+        add_listener!(TeeListener::new(WarnOnlyListener(teed.clone()), CollectingListener(everything.clone())));
+        log!(LogLevel::Info, "net", "Connected");
+        log!(LogLevel::Warn, "net", "Retrying");
+        assert_eq!(direct.lock().len(), 1);
+        assert_eq!(teed.lock().len(), 1);
+        assert_eq!(everything.lock().len(), 2);
+        assert_eq!(traceback!().len(), 2);
+    }
+
+    #[test]
+    fn test_auto_sanitize() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_auto_sanitize(true);
+        log!(LogLevel::Warn, "uart\x1b", "rx: \x1b[31mred\x00 café\n");
+        set_auto_sanitize(false);
+        log!(LogLevel::Warn, "uart", "raw\x00");
+        let traceback = traceback!();
+        assert_eq!(traceback.0[0].channel, "uart?");
+        assert_eq!(traceback.0[0].message, "rx: ?[31mred? caf?\n");
+        assert_eq!(traceback.0[1].message, "raw\x00");
+    }
+
+    #[test]
+    fn test_traceback_since() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let mut cursor = 0;
+        let mut received = Vec::new();
+        for batch in [vec!["a", "b"], vec![], vec!["c"]] {
+            for message in batch {
+                log!(LogLevel::Info, "poll", message);
+            }
+            let new_logs = get_logs_traceback_since(cursor);
+            cursor = new_logs.max_sequence().unwrap_or(cursor);
+            received.extend(new_logs.into_iter().map(|log| log.message));
+        }
+        assert_eq!(received, vec!["a", "b", "c"]);
+        assert!(get_logs_traceback_since(cursor).is_empty());
+    }
+
+    #[test]
+    fn test_log_writer() {
+        use core::fmt::Write;
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let mut writer = log_write!(LogLevel::Warn, "adc");
+        for channel in 0..3 {
+            write!(writer, "ch{}={} ", channel, channel * 10).unwrap();
+        }
+        writer.flush();
+        write!(log_write!(LogLevel::Error, "net"), "errno={}", 104).unwrap();
+        drop(log_write!(LogLevel::Error, "net"));
+        let traceback = traceback!();
+        assert_eq!(traceback.len(), 2);
+        assert_eq!(traceback.0[0].message, "ch0=0 ch1=10 ch2=20 ");
+        assert_eq!(traceback.0[0].file, Some(file!()));
+        assert_eq!(traceback.0[1].message, "errno=104");
+    }
+
+    #[test]
+    fn test_channel_allowlist() {
+        let _guard = TEST_LOCK.lock();
+        init!();
+        set_channel_allowlist(&["motor"]);
+        log!(LogLevel::Info, "motor", "Started");
+        log!(LogLevel::Info, "telemetry", "Dropped");
+        log!(LogLevel::Info, ["telemetry", "motor"], "Kept");
+        clear_channel_allowlist();
+        log!(LogLevel::Info, "telemetry", "Kept again");
+        let messages: Vec<String> = traceback!().into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, vec!["Started", "Kept", "Kept again"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_init_from_env() {
+        let _guard = TEST_LOCK.lock();
+        std::env::set_var("BREADCRUMBS_LEVEL", "Error");
+        std::env::set_var("BREADCRUMBS_CHANNELS", "motor, net");
+        assert_eq!(init_from_env(), Ok(()));
+        assert_eq!(get_global_min_level(), LogLevel::Error);
+        assert!(!is_channel_silenced("net"));
+        assert!(is_channel_silenced("telemetry"));
+        std::env::set_var("BREADCRUMBS_LEVEL", "Loud");
+        assert_eq!(init_from_env(), Err(EnvInitError::InvalidLevel(UnknownLogLevel("Loud".to_string()))));
+        assert_eq!(get_global_min_level(), LogLevel::Error);
+        std::env::remove_var("BREADCRUMBS_LEVEL");
+        std::env::remove_var("BREADCRUMBS_CHANNELS");
+        assert_eq!(init_from_env(), Ok(()));
+        assert_eq!(get_global_min_level(), LogLevel::Verbose);
+        assert!(!is_channel_silenced("telemetry"));
+    }
+
+    #[test]
+    fn test_async_listener() {
+        use core::future::Future;
+        let _guard = TEST_LOCK.lock();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (listener, driver) = AsyncListener::new(CollectingListener(seen.clone()), 2);
+        let mut driver = core::pin::pin!(driver);
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        init!(listener);
+        log!("First");
+        log!("Second");
+        log!("Dropped while full");
+        assert!(seen.lock().is_empty());
+        assert_eq!(driver.dropped_count(), 1);
+        assert!(driver.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(seen.lock().len(), 2);
+        log!("Third");
+        init!();
+        assert!(driver.as_mut().poll(&mut cx).is_ready());
+        let messages: Vec<String> = seen.lock().iter().map(|log| log.message.clone()).collect();
+        assert_eq!(messages, vec!["First", "Second", "Third"]);
+        assert_eq!(driver.dropped_count(), 1);
+    }
+
+    #[test]
+    fn test_swap_listener_keeps_logs_and_other_listeners() {
+        let _guard = TEST_LOCK.lock();
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let extra = Arc::new(Mutex::new(Vec::new()));
+        init!(CollectingListener(first.clone()));
+        add_listener!(CollectingListener(extra.clone()));
+        log!("Before swap");
+        assert!(swap_listener!(CollectingListener(second.clone())).is_some());
+        log!("After swap");
+
+        assert_eq!(traceback!().len(), 2);
+        assert_eq!(first.lock().len(), 1);
+        assert_eq!(second.lock().len(), 1);
+        assert_eq!(second.lock()[0].message, "After swap");
+        assert_eq!(extra.lock().len(), 2);
+
+        init!();
+        assert!(swap_listener!(CollectingListener(first.clone())).is_none());
+        assert!(swap_listener!(CollectingListener(first.clone())).is_some());
+        init!();
+    }
+
+    #[test]
+    fn test_log_measure() {
+        static TICKS: AtomicU64 = AtomicU64::new(0);
+        fn ticks() -> u64 {
+            TICKS.fetch_add(10, Ordering::Relaxed)
+        }
+
+        let _guard = TEST_LOCK.lock();
+        init!();
+        let value = log_measure!("perf", "untimed", { 7 });
+        assert_eq!(value, 7);
+        set_clock(ticks);
+        let mut label_evaluations = 0;
+        log_measure!("perf", { label_evaluations += 1; "spi_transfer" }, {});
+        assert_eq!(label_evaluations, 1);
+        let ticks_before = TICKS.load(Ordering::Relaxed);
+        silence_channel("perf");
+        log_measure!("perf", "silenced", {});
+        unsilence_channel("perf");
+        clear_clock();
+        assert_eq!(TICKS.load(Ordering::Relaxed), ticks_before);
+
+        let messages: Vec<String> = traceback_channel!("perf").into_iter().map(|log| log.message).collect();
+        assert_eq!(messages, ["→ untimed", "← untimed", "→ spi_transfer", "← spi_transfer (10 ticks)"]);
+        assert!(traceback!().0.iter().all(|log| log.level == LogLevel::Verbose));
+    }
+
+    struct ReconfiguringListener {
+        remove: Arc<Mutex<Option<ListenerId>>>,
+        added: Arc<Mutex<Vec<Log>>>,
+        received: Arc<Mutex<Vec<Log>>>,
+    }
+
+    impl LogListener for ReconfiguringListener {
+        fn on_log(&mut self, log: Log) {
+            self.received.lock().push(log);
+        }
+
+        fn on_flush(&mut self) {
+            if let Some(id) = self.remove.lock().take() {
+                remove_listener!(id);
+            }
+            add_listener!(CollectingListener(self.added.clone()), priority = 10);
+            log!("Reconfigured");
+        }
+    }
 
+    #[test]
+    fn test_listener_changes_during_flush_are_kept() {
+        let _guard = TEST_LOCK.lock();
+        let remove = Arc::new(Mutex::new(None));
+        let added = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        init!(ReconfiguringListener { remove: remove.clone(), added: added.clone(), received: received.clone() });
+        *remove.lock() = Some(add_listener!(CollectingListener(removed.clone())));
+        clear_logs!();
+        log!("After flush");
 
+        let messages: Vec<String> = received.lock().iter().map(|log| log.message.clone()).collect();
+        assert_eq!(messages, ["Reconfigured", "After flush"]);
+        assert_eq!(added.lock().len(), 2);
+        assert_eq!(removed.lock().len(), 0);
+        init!();
+    }
+}
 
+// Tests that do not touch the global logging state, so they also run with the `no_global` feature
 #[cfg(test)]
-mod tests {
+mod local_tests {
+    extern crate std;
     use super::*;
     use alloc::vec;
     use crate::alloc::string::ToString;
-    
+
     // Test the LogLevel enum
     #[test]
+    #[allow(deprecated)]
     fn test_log_level_enum() {
         assert_eq!(LogLevel::from_str("Verbose"), LogLevel::Verbose);
         assert_eq!(LogLevel::from_str("Info"), LogLevel::Info);
@@ -431,113 +7963,264 @@ mod tests {
         assert_eq!(LogLevel::from_str("Critical"), LogLevel::Critical);
     }
 
-    // Test Log and LogListener
-    struct MockLogListener {
-        received_log: Option<Log>,
+    #[test]
+    fn test_log_level_parsing() {
+        assert_eq!(LogLevel::try_from_str("Verbose"), Ok(LogLevel::Verbose));
+        assert_eq!(LogLevel::try_from_str("Critical"), Ok(LogLevel::Critical));
+        assert_eq!(LogLevel::try_from_str("critical"), Err(UnknownLogLevel(String::from("critical"))));
+        assert_eq!("Error".parse::<LogLevel>(), Ok(LogLevel::Error));
+        assert_eq!(UnknownLogLevel(String::from("Loud")).to_string(), "unknown log level: Loud");
     }
 
-    impl MockLogListener {
-        fn new() -> Self {
-            MockLogListener { received_log: None }
+    #[test]
+    fn test_log_level_numeric() {
+        for n in 0..6 {
+            let level = LogLevel::from_numeric(n).unwrap();
+            assert_eq!(level.numeric(), n);
+            assert_eq!(LogLevel::try_from(n), Ok(level));
         }
+        assert_eq!(LogLevel::from_numeric(6), None);
+        assert_eq!(LogLevel::try_from(255), Err(UnknownLogLevelNumber(255)));
     }
 
-    impl LogListener for MockLogListener {
-        fn on_log(&mut self, log: Log) {
-            self.received_log = Some(log);
-        }
+    #[test]
+    #[allow(deprecated)]
+    fn test_log_level_debug() {
+        assert!(LogLevel::Debug > LogLevel::Verbose && LogLevel::Debug < LogLevel::Info);
+        assert!(LogLevel::Debug.is_at_least(LogLevel::Verbose));
+        assert!(!LogLevel::Debug.is_at_least(LogLevel::Info));
+        assert_eq!("Debug".parse::<LogLevel>(), Ok(LogLevel::Debug));
+        assert_eq!(LogLevel::Debug.to_string(), "Debug");
+        let legacy: Vec<Option<LogLevel>> = (0..6).map(LogLevel::from_legacy_numeric).collect();
+        assert_eq!(legacy, vec![Some(LogLevel::Verbose), Some(LogLevel::Info), Some(LogLevel::Warn), Some(LogLevel::Error), Some(LogLevel::Critical), None]);
     }
 
-    // Wrapper struct that implements LogListener for Arc<Mutex<MockLogListener>>
-    struct MockLogListenerWrapper(Arc<Mutex<MockLogListener>>);
+    #[test]
+    fn test_traceback_formatting() {
+        let mut first = Log::new(String::from("net"), LogLevel::Warn, String::from("Log 1"));
+        first.sequence = 9;
+        first.timestamp = Some(100);
+        let mut second = Log::new(String::from(""), LogLevel::Error, String::from("Log 2"));
+        second.sequence = 10;
+        let traceback = Traceback(vec![first, second]);
 
-    impl LogListener for MockLogListenerWrapper {
-        fn on_log(&mut self, log: Log) {
-            self.0.lock().on_log(log);
-        }
+        assert_eq!(format!("{:#}", traceback), " 9 100 Warn     net Log 1\n10     Error        Log 2\n");
+        assert_eq!(format!("{}", traceback), "[100/net/Warn    ] Log 1\n[Error   ] Log 2\n");
+        assert!(format!("{:?}", traceback).starts_with("Traceback { logs: [Log { channel: \"net\""));
     }
 
     #[test]
-    fn test_log_creation_and_handling() {
-        let mock_listener = Arc::new(Mutex::new(MockLogListener::new()));
-        let mock_listener_wrapper = MockLogListenerWrapper(mock_listener.clone());
-        init!(mock_listener_wrapper);
+    fn test_log_builder() {
+        let log = Log::builder().channel("net").level(LogLevel::Warn).message(String::from("Test log message")).build();
+        assert_eq!(log.channel, "net");
+        assert_eq!(log.level, LogLevel::Warn);
+        assert_eq!(log.message, "Test log message");
+        assert_eq!(Log::builder().build(), Log::new(String::new(), LogLevel::Info, String::new()));
+    }
 
-        log!(LogLevel::Info, "test_channel", "Test log message");
+    #[test]
+    fn test_log_conversions() {
+        let log = Log::new(String::from("net"), LogLevel::Error, String::from("connect failed"));
+        let (channel, level, message): (String, LogLevel, String) = log.clone().into();
+        assert_eq!((channel.as_str(), level, message.as_str()), ("net", LogLevel::Error, "connect failed"));
+        assert_eq!(Log::from((channel, level, message)), log);
+        assert_eq!(String::from(&log), "[net/Error   ] connect failed");
 
-        let received_log = mock_listener.lock().received_log.clone().expect("Log not received by listener");
-        assert_eq!(received_log.level, LogLevel::Info);
-        assert_eq!(received_log.channel, "test_channel");
-        assert_eq!(received_log.message, "Test log message");
+        assert_eq!(Log::try_from(String::from(&log).as_str()), Ok(log));
+        assert_eq!(Log::try_from("[Warn    ] Test log message").unwrap().level, LogLevel::Warn);
+        assert_eq!(Log::try_from("[Info] Test log message"), Ok(Log::new(String::new(), LogLevel::Info, String::from("Test log message"))));
+        assert_eq!(Log::try_from("[a/b/Warn] Test log message").unwrap().channel, "a/b");
+        assert_eq!(Log::try_from("Test log message"), Err(ParseLogError::MissingBracket));
+        assert_eq!(Log::try_from("[net/Error Test log message"), Err(ParseLogError::MissingBracket));
+        assert_eq!(Log::try_from("[net] Test log message"), Err(ParseLogError::MissingSlash));
+        assert_eq!(Log::try_from("[net/Loud] Test log message"), Err(ParseLogError::UnknownLevel));
+        assert_eq!(Log::try_from("[net/Error] "), Err(ParseLogError::EmptyMessage));
     }
 
-    // Test traceback generation
     #[test]
-    fn test_traceback_generation() {
-        log!(LogLevel::Info, "channel1", "Log 1");
-        log!(LogLevel::Warn, "channel2", "Log 2");
-        log!(LogLevel::Error, "channel1", "Log 3");
+    fn test_traceback_json() {
+        let traceback = Traceback(vec![
+            Log::new(String::from("net"), LogLevel::Error, String::from("line 1\nline 2 \"quoted\" \\ \u{1}")),
+            Log::new(String::new(), LogLevel::Info, String::from("Test log message")),
+        ]);
+        let json = traceback.to_json();
+        assert_eq!(json, r#"[{"channel":"net","level":"Error","message":"line 1\nline 2 \"quoted\" \\ \u0001"},{"channel":"","level":"Info","message":"Test log message"}]"#);
+        assert_eq!(Traceback::from_json(&json).unwrap().0, traceback.0);
 
-        let traceback = traceback!(LogLevel::Warn, "channel2").to_string();
-        assert!(traceback.contains("[channel2/Warn] Log 2"));
-        assert!(!traceback.contains("[channel1/Info] Log 1"));
-        assert!(!traceback.contains("[channel1/Error] Log 3"));
+        assert_eq!(Traceback::from_json(" [ ] ").unwrap().0, vec![]);
+        assert_eq!(Traceback::from_json(r#"[{"level":"Warn","message":"\u00e9","channel":"x"}]"#).unwrap().0[0].message, "é");
+        assert_eq!(Traceback::from_json("["), Err(JsonParseError::UnexpectedEnd));
+        assert_eq!(Traceback::from_json("[}"), Err(JsonParseError::UnexpectedCharacter(1)));
+        assert_eq!(Traceback::from_json(r#"[{"channel":"\q"}]"#), Err(JsonParseError::InvalidEscape(13)));
+        assert_eq!(Traceback::from_json(r#"[{"channel":"","level":"Loud","message":""}]"#), Err(JsonParseError::UnknownLevel(String::from("Loud"))));
+        assert_eq!(Traceback::from_json(r#"[{"channel":"","level":"Info"}]"#), Err(JsonParseError::MissingField("message")));
+        assert_eq!(Traceback::from_json(r#"[{"file":""}]"#), Err(JsonParseError::UnknownField(String::from("file"))));
     }
 
-    // Test log macros
+    #[cfg(feature = "heapless")]
     #[test]
-    fn test_log_macros() {
-        log!(LogLevel::Info, "test_channel", "Test log message");
-        log_level!(LogLevel::Info, "Test log message");
-        log_channel!("test_channel", "Test log message 2");
-
-        let traceback = traceback!().to_string();
-        assert!(traceback.contains("[test_channel/Info] Test log message 2"));
-        assert!(traceback.contains("[test_channel/Info] Test log message "));
-        assert!(traceback.contains("[Info] Test log message"));
+    fn test_bounded_log_store() {
+        let mut store = BoundedLogStore::<2, 4>::new();
+        store.push(Log::new_info("net", "Connected"));
+        store.push(Log::new_warn("net", "Slow"));
+        store.push(Log::new_error("net", "Lost"));
+        let messages: Vec<&str> = store.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, ["Slow", "Lost"]);
+        assert_eq!(store.last().unwrap().level, LogLevel::Error);
+        store.push(Log::new_info("net", "Reconnected"));
+        assert_eq!(store.last().unwrap().message, "Reco…");
+        store.remove_at(0);
+        assert_eq!(store.len(), 1);
     }
 
-    // Test the example in the README
+    #[cfg(feature = "serde")]
     #[test]
-    fn read_me_example() {
-        init!();
+    fn test_serde_round_trip() {
+        let log = Log::new("c".to_string(), LogLevel::Info, "m".to_string());
+        assert_eq!(serde_json::to_string(&log).unwrap(), r#"{"channel":"c","level":"Info","message":"m"}"#);
 
-        log!("Hello, world!");
-        log_level!(LogLevel::Info, "Test log message");
-        log_channel!("test_channel", "Test log message");
-        log!(LogLevel::Info, "test_channel", "Test log message");
+        let mut stored = log.with_context("port", "443");
+        stored.sequence = 7;
+        stored.file = Some(file!());
+        let traceback = Traceback(vec![log, stored.clone()]);
+        let json = serde_json::to_string(&traceback).unwrap();
+        let parsed: Traceback = serde_json::from_str(&json).unwrap();
+        stored.file = None;
+        assert_eq!(parsed.0[1], stored);
+
+        let parsed: Log = serde_json::from_str(r#"{"channel":"c","level":"wARN","message":"m"}"#).unwrap();
+        assert_eq!(parsed.level, LogLevel::Warn);
+        let parsed: LogLevel = serde_json::from_str(r#""Loud""#).unwrap();
+        assert_eq!(parsed, LogLevel::Info);
     }
 
-    struct MyLogListener2 {
-        success: bool,
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_log_channel() {
+        #[derive(LogChannel)]
+        #[allow(dead_code)]
+        enum DerivedChannel {
+            Network,
+            Storage(u8),
+            Sensor { id: u8 },
+        }
+
+        assert_eq!(DerivedChannel::Network.channel_name(), "Network");
+        assert_eq!(DerivedChannel::Storage(1).channel_name(), "Storage");
+        assert_eq!(DerivedChannel::Sensor { id: 2 }.channel_name(), "Sensor");
     }
 
-    impl LogListener for MyLogListener2 {
-        fn on_log(&mut self, log: Log) {
-            if log.level.is_at_least(LogLevel::Warn) {
-                self.success = true;
-            }
+    #[test]
+    fn test_all_levels_match_numeric() {
+        for (n, level) in LogLevel::all_levels().enumerate() {
+            assert_eq!(LogLevel::from_numeric(n as u8), Some(level));
         }
+        assert_eq!(LogLevel::from_numeric(ALL_LOG_LEVELS.len() as u8), None);
+        assert!(ALL_LOG_LEVELS.windows(2).all(|pair| pair[0] < pair[1]));
     }
 
-    struct MockLogListenerWrapper2(Arc<Mutex<MyLogListener2>>);
+    #[test]
+    fn test_traceback_from_logs() {
+        let logs = vec![
+            Log::new("net".to_string(), LogLevel::Warn, "Retrying".to_string()),
+            Log::new("net".to_string(), LogLevel::Info, "Connected".to_string()),
+        ];
+        let from_slice: Traceback = logs[..1].into();
+        assert_eq!(from_slice.as_logs(), &logs[..1]);
+        let from_vec: Traceback = logs.clone().into();
+        assert_eq!(from_vec.into_logs(), logs);
+    }
 
-    impl LogListener for MockLogListenerWrapper2 {
-        fn on_log(&mut self, log: Log) {
-            self.0.lock().on_log(log);
-        }
+    #[test]
+    fn test_traceback_collect_and_extend() {
+        let logs = vec![
+            Log::new("net".to_string(), LogLevel::Warn, "Retrying".to_string()),
+            Log::new("net".to_string(), LogLevel::Info, "Connected".to_string()),
+        ];
+        let mut traceback: Traceback = logs.iter().take(1).cloned().collect();
+        assert_eq!(traceback.as_logs(), &logs[..1]);
+        traceback.extend(logs[1..].iter().cloned());
+        assert_eq!(traceback.into_logs(), logs);
     }
 
+    #[cfg(feature = "ansi-colors")]
     #[test]
-    fn no_std_readme_example() {
-        let log_handler = Arc::new(Mutex::new(MyLogListener2 { success: false }));
-        let log_handler_wrapper = MockLogListenerWrapper2(log_handler.clone());
+    fn test_ansi_colors() {
+        let traceback = Traceback(vec![
+            Log::new("net".to_string(), LogLevel::Critical, "Link down".to_string()),
+            Log::new(String::new(), LogLevel::Verbose, "Tick".to_string()),
+        ]);
+        assert_eq!(
+            traceback.to_ansi_string(),
+            "[net/\x1b[1;91mCritical\x1b[0m] Link down\n[\x1b[37mVerbose \x1b[0m] Tick\n"
+        );
+        assert_eq!(traceback.to_string(), "[net/Critical] Link down\n[Verbose ] Tick\n");
+        assert_eq!(format!("{}", traceback.colorize(false)), traceback.to_string());
+        assert_eq!(format!("{:#}", traceback.colorize(false)), format!("{:#}", traceback));
+        assert_eq!(format!("{:#}", traceback.colorize(true)), "1 \x1b[1;91mCritical\x1b[0m net Link down\n2 \x1b[37mVerbose \x1b[0m     Tick\n");
+    }
 
-        init!(log_handler_wrapper);
+    #[test]
+    fn test_persist_listener_truncates_into_fixed_records() {
+        let mut persist = PersistListener::new(Vec::new(), 4);
+        persist.on_log(Log::new(String::from("net"), LogLevel::Error, String::from("Link down")));
+        persist.on_log(Log::new("c".repeat(70), LogLevel::Info, String::from("ok")));
+        let bytes = persist.into_inner();
+        let len = PersistListener::<Vec<u8>>::record_len(4);
+        assert_eq!(bytes.len(), 2 * len);
 
-        log!(LogLevel::Error, "test_channel", "Test log message");
+        let (first, second) = bytes.split_at(len);
+        assert_eq!(first[0], LogLevel::Error.numeric());
+        assert_eq!(first[1], 3);
+        assert_eq!(&first[2..5], b"net");
+        assert_eq!(&first[65..67], &4u16.to_le_bytes());
+        assert_eq!(&first[67..71], b"Link");
 
-        assert!(log_handler.lock().success);
+        assert_eq!(second[1], 63);
+        assert_eq!(&second[65..67], &2u16.to_le_bytes());
+        assert_eq!(&second[67..71], b"ok\0\0");
+    }
+
+    #[test]
+    fn test_logger_without_globals() {
+        let logger = Logger::with_capacity(2);
+        local_log!(&logger, LogLevel::Info, "motor", "Started");
+        local_log!(&logger, LogLevel::Error, "motor", "Stalled at {}", 3);
+        local_log!(&logger, "No channel");
+        let traceback = logger.traceback(None, None);
+        assert_eq!(traceback.len(), 2);
+        assert_eq!(traceback.0[0].message, "Stalled at 3");
+        assert_eq!(logger.last_log(None).unwrap().message, "No channel");
+    }
+
+    static ECHO_LOGGER: Logger = Logger::new();
+
+    struct EchoListener(Arc<Mutex<Vec<String>>>);
+
+    impl LogListener for EchoListener {
+        fn on_init(&mut self) {
+            local_log!(&ECHO_LOGGER, "init");
+        }
+
+        fn on_log(&mut self, log: Log) {
+            if log.message == "ping" {
+                local_log!(&ECHO_LOGGER, "pong");
+            }
+            self.0.lock().push(log.message);
+        }
+
+        fn on_flush(&mut self) {
+            local_log!(&ECHO_LOGGER, "flushed");
+        }
     }
-}
 
+    #[test]
+    fn test_logger_listener_can_log_to_its_logger() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        ECHO_LOGGER.set_listener(Box::new(EchoListener(seen.clone())));
+        local_log!(&ECHO_LOGGER, "ping");
+        ECHO_LOGGER.clear_logs();
+        assert_eq!(*seen.lock(), ["init", "ping", "pong", "flushed"]);
+        assert_eq!(ECHO_LOGGER.len(), 1);
+    }
+}